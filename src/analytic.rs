@@ -0,0 +1,322 @@
+use std::f64::consts::PI;
+
+use crate::lensing::{End, Hit};
+
+
+// == Elliptic integrals and functions, parameter m = k^2 ==
+
+// Carlson's symmetric integral R_F, by duplication
+fn carlson_rf(mut x: f64, mut y: f64, mut z: f64) -> f64 {
+    for _ in 0..100 {
+        let mu = (x + y + z)/3.0;
+        let dev = ((x - mu).abs()).max((y - mu).abs()).max((z - mu).abs()) / mu;
+        if dev < 1e-4 {
+            let (dx, dy) = (1.0 - x/mu, 1.0 - y/mu);
+            let dz = -(dx + dy);
+            let (e2, e3) = (dx*dy - dz*dz, dx*dy*dz);
+            return (1.0 - e2/10.0 + e3/14.0 + e2*e2/24.0 - 3.0*e2*e3/44.0) / mu.sqrt();
+        }
+
+        let lambda = (x*y).sqrt() + (y*z).sqrt() + (z*x).sqrt();
+        x = (x + lambda)/4.0;
+        y = (y + lambda)/4.0;
+        z = (z + lambda)/4.0;
+    }
+
+    1.0/((x + y + z)/3.0).sqrt()
+}
+
+// Complete elliptic integral of the first kind K(m)
+pub fn elliptic_k(m: f64) -> f64 {
+    carlson_rf(0.0, 1.0 - m, 1.0)
+}
+
+// Incomplete elliptic integral of the first kind F(phi | m), for any phi
+pub fn elliptic_f(phi: f64, m: f64) -> f64 {
+    let periods = (phi/PI).round();
+    let s = (phi - periods*PI).sin();
+
+    s * carlson_rf(1.0 - s*s, 1.0 - m*s*s, 1.0) + 2.0 * periods * elliptic_k(m)
+}
+
+// Jacobi amplitude am(x | m), the inverse of F, by the AGM
+pub fn jacobi_am(x: f64, m: f64) -> f64 {
+    let (mut a, mut c) = (vec![1.0], vec![m.sqrt()]);
+    let mut b = (1.0 - m).sqrt();
+    while c[c.len() - 1].abs() > 1e-15 && a.len() < 40 {
+        let an = a[a.len() - 1];
+        a.push((an + b)/2.0);
+        c.push((an - b)/2.0);
+        b = (an*b).sqrt();
+    }
+
+    let n = a.len() - 1;
+    let mut phi = 2f64.powi(n as i32) * a[n] * x;
+    for i in (1..=n).rev() {
+        phi = (phi + (c[i]/a[i] * phi.sin()).asin())/2.0;
+    }
+    phi
+}
+
+
+// == Schwarzschild null geodesics, (du/dψ)^2 = 1/b^2 - u^2 + 2 M u^3 ==
+
+// Rays with a smaller impact parameter fall in
+pub fn critical_impact_parameter(mass: f64) -> f64 {
+    3.0 * 3f64.sqrt() * mass
+}
+
+// Closest approach P of a ray with impact parameter `b` coming from
+// infinity, the largest root of P^3 - b^2 P + 2 M b^2
+pub fn turning_point(mass: f64, b: f64) -> Option<f64> {
+    if b <= critical_impact_parameter(mass) {
+        return None;
+    }
+
+    Some(2.0*b/3f64.sqrt() * ((-critical_impact_parameter(mass)/b).acos()/3.0).cos())
+}
+
+// Total deflection of a ray with impact parameter `b` from infinity to
+// infinity, following Luminet (1979)
+pub fn deflection(mass: f64, b: f64) -> Option<f64> {
+    let scattering = Scattering::new(mass, b)?;
+    Some(2.0 * (elliptic_k(scattering.m) - scattering.x_infinity) / scattering.rate() - PI)
+}
+
+// Orbit with a turning point, u(x) = (sn^2(x) (Q - P + 6M) - (Q - P + 2M))/(4MP)
+// with x = sqrt(Q/P) ψ/2 + const
+#[derive(Clone, Copy, Debug)]
+struct Scattering {
+    mass: f64,
+    p: f64,
+    q: f64,
+    m: f64,
+    x_infinity: f64, // x at u = 0 before the turning point
+}
+
+impl Scattering {
+    fn new(mass: f64, b: f64) -> Option<Scattering> {
+        let p = turning_point(mass, b)?;
+        let q = ((p - 2.0*mass) * (p + 6.0*mass)).sqrt();
+        let m = (q - p + 6.0*mass) / (2.0*q);
+        let x_infinity = elliptic_f(((q - p + 2.0*mass) / (q - p + 6.0*mass)).sqrt().asin(), m);
+
+        Some(Scattering {mass, p, q, m, x_infinity})
+    }
+
+    fn rate(&self) -> f64 {
+        (self.q/self.p).sqrt()/2.0
+    }
+
+    // x at `u` before the turning point
+    fn x(&self, u: f64) -> f64 {
+        let (mass, p, q) = (self.mass, self.p, self.q);
+        let sn2 = (4.0*mass*p*u + q - p + 2.0*mass) / (q - p + 6.0*mass);
+        elliptic_f(sn2.clamp(0.0, 1.0).sqrt().asin(), self.m)
+    }
+
+    fn u(&self, x: f64) -> f64 {
+        let (mass, p, q) = (self.mass, self.p, self.q);
+        let sn = jacobi_am(x, self.m).sin();
+        (sn*sn*(q - p + 6.0*mass) - (q - p + 2.0*mass)) / (4.0*mass*p)
+    }
+}
+
+// Orbit without a turning point, falling from or escaping to infinity. With a
+// the real root of 2M u^3 - u^2 + 1/b^2 and A^2 = (m_c - a)^2 + n^2 from the
+// complex ones m_c ± i n, u(x) = a + A (1 - cn(x))/(1 + cn(x)) with
+// x = sqrt(2 M A) ψ + const (Byrd & Friedman 239.00)
+#[derive(Clone, Copy, Debug)]
+struct Plunging {
+    mass: f64,
+    a: f64,
+    big_a: f64,
+    m: f64,
+}
+
+impl Plunging {
+    fn new(mass: f64, b: f64) -> Plunging {
+        // Newton from the left of the root, where the cubic is increasing and
+        // concave, converges monotonically
+        let mut a = -2.0/b;
+        for _ in 0..100 {
+            let f = 2.0*mass*a.powi(3) - a*a + 1.0/(b*b);
+            let step = f / (6.0*mass*a*a - 2.0*a);
+            a -= step;
+            if step.abs() < 1e-15 * a.abs() {
+                break;
+            }
+        }
+
+        // 2M (u - a)(u^2 - 2 m_c u + m_c^2 + n^2)
+        let m_c = (1.0/(2.0*mass) - a)/2.0;
+        let modulus2 = -1.0/(2.0*mass*a*b*b);
+        let big_a = (modulus2 - 2.0*a*m_c + a*a).sqrt();
+        let m = (big_a + m_c - a) / (2.0*big_a);
+
+        Plunging {mass, a, big_a, m}
+    }
+
+    fn rate(&self) -> f64 {
+        (2.0*self.mass*self.big_a).sqrt()
+    }
+
+    fn x(&self, u: f64) -> f64 {
+        let d = u - self.a;
+        elliptic_f(((self.big_a - d) / (self.big_a + d)).clamp(-1.0, 1.0).acos(), self.m)
+    }
+
+    fn u(&self, x: f64) -> f64 {
+        let cn = jacobi_am(x, self.m).cos();
+        self.a + self.big_a * (1.0 - cn) / (1.0 + cn)
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+enum Kind {
+    Scattering(Scattering),
+    Plunging(Plunging),
+}
+
+// Exact orbit of the photon leaving radius `r` at angle `alpha` from the
+// direction to the hole, with the same conventions as the lensing map.
+// Escaping rays end in their direction at infinity.
+#[derive(Clone, Copy, Debug)]
+pub struct Orbit {
+    kind: Kind,
+    x_cam: f64,
+    rate: f64, // dx/dψ, negative if x decreases along the ray
+    psi_end: f64,
+    end: End,
+}
+
+impl Orbit {
+    // None for radial rays, and for those trapped between the horizon and
+    // the photon sphere
+    pub fn new(mass: f64, r: f64, alpha: f64) -> Option<Orbit> {
+        let rs = 2.0 * mass;
+        if alpha.sin() < 1e-12 || r <= rs {
+            return None;
+        }
+
+        let b = r * alpha.sin() / (1.0 - rs/r).sqrt();
+        let inward = alpha < PI/2.0;
+
+        match Scattering::new(mass, b) {
+            Some(scattering) => {
+                if 1.0/r > 1.0/scattering.p * (1.0 + 1e-12) {
+                    return None;
+                }
+
+                // Past the turning point x keeps growing, sn^2 being symmetric about K
+                let k = elliptic_k(scattering.m);
+                let x_cam = if inward { scattering.x(1.0/r) } else { 2.0*k - scattering.x(1.0/r) };
+                let psi_end = (2.0*k - scattering.x_infinity - x_cam) / scattering.rate();
+
+                Some(Orbit {kind: Kind::Scattering(scattering), x_cam, rate: scattering.rate(), psi_end, end: End::Escaped(psi_end)})
+            },
+            None => {
+                let plunging = Plunging::new(mass, b);
+                let x_cam = plunging.x(1.0/r);
+                let (rate, x_end, end) = if inward {
+                    (plunging.rate(), plunging.x(1.0/rs), End::Captured)
+                } else {
+                    (-plunging.rate(), plunging.x(0.0), End::Escaped(0.0))
+                };
+
+                let psi_end = (x_end - x_cam) / rate;
+                let end = match end {
+                    End::Escaped(_) => End::Escaped(psi_end),
+                    End::Captured => End::Captured,
+                };
+
+                Some(Orbit {kind: Kind::Plunging(plunging), x_cam, rate, psi_end, end})
+            },
+        }
+    }
+
+    // Radius at the in-plane angle `psi`, if the orbit gets that far
+    pub fn r(&self, psi: f64) -> Option<f64> {
+        if psi < 0.0 || psi > self.psi_end {
+            return None;
+        }
+
+        let x = self.x_cam + self.rate * psi;
+        let u = match self.kind {
+            Kind::Scattering(scattering) => scattering.u(x),
+            Kind::Plunging(plunging) => plunging.u(x),
+        };
+        Some(1.0/u)
+    }
+
+    pub fn end(&self) -> End {
+        self.end
+    }
+
+    // Azimuths ψ, and radii, at which the orbit crosses the equator, every
+    // half turn from `crossing`
+    pub fn crossings(&self, crossing: f64) -> impl Iterator<Item = (f64, f64)> + '_ {
+        (0..).map(move |k| crossing + k as f64 * PI)
+            .map_while(move |psi| self.r(psi).map(|r| (psi, r)))
+    }
+
+    // First crossing of the equator accepted by `disk`, else where the orbit ends
+    pub fn hit<F: Fn(f64) -> bool>(&self, crossing: Option<f64>, disk: F) -> Hit {
+        crossing.and_then(|crossing| self.crossings(crossing).map(|(_, r)| r).find(|&r| disk(r)))
+            .map_or(Hit::End(self.end), Hit::Disk)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use rand::prelude::*;
+
+    use crate::binet;
+
+    #[test]
+    fn elliptic_functions_invert() {
+        assert!((elliptic_k(0.0) - PI/2.0).abs() < 1e-12);
+
+        let (phi, m) = (3.0 * random::<f64>(), 0.99 * random::<f64>());
+        assert!((jacobi_am(elliptic_f(phi, m), m) - phi).abs() < 1e-9, "Failed at {:?}", (phi, m));
+
+        // K(1/2) = Γ(1/4)^2 / (4 sqrt(π))
+        assert!((elliptic_k(0.5) - 1.854_074_677_301_372).abs() < 1e-12);
+    }
+
+    #[test]
+    fn weak_field_deflection() {
+        let mass = 0.5;
+        let b = 1e3 * (1.0 + random::<f64>());
+
+        // 4M/b + 15π/4 (M/b)^2
+        let expected = 4.0*mass/b + 15.0*PI/4.0*(mass/b).powi(2);
+        assert!((deflection(mass, b).unwrap() / expected - 1.0).abs() < 1e-4, "Failed at {}", b);
+
+        let p = turning_point(mass, b).unwrap();
+        assert!((p.powi(3) / (p - 2.0*mass) - b*b).abs() < 1e-9 * b*b);
+    }
+
+    #[test]
+    fn binet_matches_analytic() {
+        let mass = 0.5;
+        let r = 5.0 + 20.0 * random::<f64>();
+        let r_escape = 1e3;
+
+        for _ in 0..20 {
+            let alpha = PI * random::<f64>();
+            let crossing = PI * random::<f64>();
+            let orbit = Orbit::new(mass, r, alpha).unwrap();
+
+            // Crossings inside the camera, clear of where binet stops short of the horizon
+            let disk = |radius: f64| radius < r && radius > 1.1;
+            match (orbit.hit(Some(crossing), disk), binet::trace(mass, r, alpha, r_escape, Some(crossing), disk)) {
+                (Hit::Disk(a), Hit::Disk(b)) => assert!((a - b).abs() < 1e-6 * a, "Failed at {:?}", (r, alpha)),
+                (Hit::End(End::Escaped(a)), Hit::End(End::Escaped(b))) => assert!((a - b).abs() < 1e-2, "Failed at {:?}", (r, alpha)),
+                (a, b) => assert_eq!(a, b, "Failed at {:?}", (r, alpha)),
+            }
+        }
+    }
+}
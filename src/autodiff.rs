@@ -0,0 +1,167 @@
+use std::ops::{Add, Sub, Mul, Div, Neg};
+
+// Dual number re + eps*ε with ε² = 0. Evaluating a function on
+// `Dual::variable(x)` gives f(x) in `re` and f'(x) in `eps`, exact up to
+// rounding.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Dual {
+    pub re: f64,
+    pub eps: f64,
+}
+
+impl Dual {
+    pub fn new(re: f64, eps: f64) -> Dual {
+        Dual {re, eps}
+    }
+
+    pub fn constant(re: f64) -> Dual {
+        Dual::new(re, 0.0)
+    }
+
+    pub fn variable(re: f64) -> Dual {
+        Dual::new(re, 1.0)
+    }
+
+    // Chain rule for f with f(re) = value and f'(re) = derivative
+    fn chain(self, value: f64, derivative: f64) -> Dual {
+        Dual::new(value, derivative * self.eps)
+    }
+}
+
+impl From<f64> for Dual {
+    fn from(re: f64) -> Dual {
+        Dual::constant(re)
+    }
+}
+
+impl Add for Dual {
+    type Output = Dual;
+    fn add(self, rhs: Dual) -> Dual {
+        Dual::new(self.re + rhs.re, self.eps + rhs.eps)
+    }
+}
+
+impl Sub for Dual {
+    type Output = Dual;
+    fn sub(self, rhs: Dual) -> Dual {
+        Dual::new(self.re - rhs.re, self.eps - rhs.eps)
+    }
+}
+
+impl Mul for Dual {
+    type Output = Dual;
+    fn mul(self, rhs: Dual) -> Dual {
+        Dual::new(self.re * rhs.re, self.eps * rhs.re + self.re * rhs.eps)
+    }
+}
+
+impl Div for Dual {
+    type Output = Dual;
+    fn div(self, rhs: Dual) -> Dual {
+        Dual::new(
+            self.re / rhs.re,
+            (self.eps * rhs.re - self.re * rhs.eps) / (rhs.re * rhs.re),
+        )
+    }
+}
+
+impl Neg for Dual {
+    type Output = Dual;
+    fn neg(self) -> Dual {
+        Dual::new(-self.re, -self.eps)
+    }
+}
+
+impl Add<f64> for Dual {
+    type Output = Dual;
+    fn add(self, rhs: f64) -> Dual {
+        Dual::new(self.re + rhs, self.eps)
+    }
+}
+
+impl Sub<f64> for Dual {
+    type Output = Dual;
+    fn sub(self, rhs: f64) -> Dual {
+        Dual::new(self.re - rhs, self.eps)
+    }
+}
+
+impl Mul<f64> for Dual {
+    type Output = Dual;
+    fn mul(self, rhs: f64) -> Dual {
+        Dual::new(self.re * rhs, self.eps * rhs)
+    }
+}
+
+impl Div<f64> for Dual {
+    type Output = Dual;
+    fn div(self, rhs: f64) -> Dual {
+        Dual::new(self.re / rhs, self.eps / rhs)
+    }
+}
+
+// Real numbers the metric components can be evaluated on
+pub trait Scalar: Copy + From<f64>
+    + Add<Output = Self> + Sub<Output = Self> + Mul<Output = Self> + Div<Output = Self> + Neg<Output = Self>
+    + Add<f64, Output = Self> + Sub<f64, Output = Self> + Mul<f64, Output = Self> + Div<f64, Output = Self>
+{
+    fn re(self) -> f64;
+
+    fn sin(self) -> Self;
+    fn cos(self) -> Self;
+    fn tan(self) -> Self;
+    fn exp(self) -> Self;
+    fn ln(self) -> Self;
+    fn sqrt(self) -> Self;
+    fn powi(self, n: i32) -> Self;
+    fn powf(self, n: f64) -> Self;
+}
+
+impl Scalar for f64 {
+    fn re(self) -> f64 { self }
+
+    fn sin(self) -> f64 { f64::sin(self) }
+    fn cos(self) -> f64 { f64::cos(self) }
+    fn tan(self) -> f64 { f64::tan(self) }
+    fn exp(self) -> f64 { f64::exp(self) }
+    fn ln(self) -> f64 { f64::ln(self) }
+    fn sqrt(self) -> f64 { f64::sqrt(self) }
+    fn powi(self, n: i32) -> f64 { f64::powi(self, n) }
+    fn powf(self, n: f64) -> f64 { f64::powf(self, n) }
+}
+
+impl Scalar for Dual {
+    fn re(self) -> f64 { self.re }
+
+    fn sin(self) -> Dual { self.chain(self.re.sin(), self.re.cos()) }
+    fn cos(self) -> Dual { self.chain(self.re.cos(), -self.re.sin()) }
+    fn tan(self) -> Dual { self.chain(self.re.tan(), 1.0/self.re.cos().powi(2)) }
+    fn exp(self) -> Dual { self.chain(self.re.exp(), self.re.exp()) }
+    fn ln(self) -> Dual { self.chain(self.re.ln(), 1.0/self.re) }
+    fn sqrt(self) -> Dual { self.chain(self.re.sqrt(), 0.5/self.re.sqrt()) }
+    fn powi(self, n: i32) -> Dual { self.chain(self.re.powi(n), n as f64 * self.re.powi(n - 1)) }
+    fn powf(self, n: f64) -> Dual { self.chain(self.re.powf(n), n * self.re.powf(n - 1.0)) }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use rand::prelude::*;
+
+    #[test]
+    fn derivatives_are_exact() {
+        let x: f64 = 0.5 + random::<f64>();
+        let f = |x: Dual| (x.sin() * x.powi(3) + x.exp() / x).sqrt();
+        let fx = f(Dual::variable(x));
+
+        let value = (x.sin() * x.powi(3) + x.exp() / x).sqrt();
+        let derivative = (
+            x.cos() * x.powi(3) + 3.0 * x.sin() * x.powi(2)
+            + x.exp() / x - x.exp() / x.powi(2)
+        ) / (2.0 * value);
+
+        assert!((fx.re - value).abs() < 1e-12);
+        assert!((fx.eps - derivative).abs() < 1e-12);
+    }
+}
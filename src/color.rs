@@ -0,0 +1,26 @@
+// 8 bit sRGB color, as shown on screen or written to an image
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Color {
+    pub r: u8,
+    pub g: u8,
+    pub b: u8,
+}
+
+impl Color {
+    pub const fn rgb(r: u8, g: u8, b: u8) -> Color {
+        Color {r, g, b}
+    }
+}
+
+impl From<Color> for image::Rgb<u8> {
+    fn from(color: Color) -> image::Rgb<u8> {
+        image::Rgb([color.r, color.g, color.b])
+    }
+}
+
+#[cfg(feature = "window")]
+impl From<Color> for sdl2::pixels::Color {
+    fn from(color: Color) -> sdl2::pixels::Color {
+        sdl2::pixels::Color::RGB(color.r, color.g, color.b)
+    }
+}
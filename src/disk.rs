@@ -0,0 +1,371 @@
+use std::f64::consts::PI;
+
+use nalgebra::Vector3;
+
+use crate::spectral;
+use crate::units::{self, C, G, SOLAR_MASS, STEFAN_BOLTZMANN, YEAR};
+
+
+// Emission of a thin accretion disk on the equatorial plane. Radii are in
+// scene units.
+pub trait Disk: Clone + Send + Sync + 'static {
+    // === Needed ==
+    fn inner_radius(&self) -> f64;
+
+    fn outer_radius(&self) -> f64;
+
+    // Temperature of the disk surface at `r`, in K
+    fn temperature(&self, r: f64) -> f64;
+
+
+    // == Optional ==
+    fn contains(&self, r: f64) -> bool {
+        r > self.inner_radius() && r < self.outer_radius()
+    }
+
+    // Observed linear sRGB radiance at `r`, in cd/m^2. `redshift` is the
+    // ratio of observed to emitted photon energy. A redshifted blackbody is a
+    // blackbody at the shifted temperature, so the bolometric intensity goes
+    // as redshift^4.
+    fn radiance(&self, r: f64, redshift: f64) -> Vector3<f64> {
+        spectral::blackbody_radiance(self.temperature(r) * redshift)
+    }
+
+    // Highest temperature on the disk, sampled
+    fn peak_temperature(&self) -> f64 {
+        let (inner, outer) = (self.inner_radius(), self.outer_radius());
+        (0..=100)
+            .map(|i| self.temperature(inner + (outer - inner) * i as f64 / 100.0))
+            .fold(0.0, f64::max)
+    }
+
+    // Scale bringing the luminance of the hottest part of the disk, as
+    // emitted, to 1
+    fn exposure(&self) -> f64 {
+        1.0 / spectral::blackbody_xyz(self.peak_temperature())[1]
+    }
+}
+
+// Radius of the innermost stable circular orbit around a Kerr hole, prograde
+// for positive `spin`
+pub fn isco(mass: f64, spin: f64) -> f64 {
+    let z1 = 1.0 + (1.0 - spin.powi(2)).cbrt()*((1.0 + spin).cbrt() + (1.0 - spin).cbrt());
+    let z2 = (3.0*spin.powi(2) + z1.powi(2)).sqrt();
+
+    mass*(3.0 + z2 - spin.signum()*((3.0 - z1)*(3.0 + z1 + 2.0*z2)).sqrt())
+}
+
+// Specific energy E, angular momentum L and angular velocity Ω of the
+// circular equatorial orbit of radius `r` around a Kerr-Newman hole, prograde
+// for positive `spin`. None inside the photon orbit, where there is none.
+pub fn kerr_newman_orbit(mass: f64, spin: f64, charge: f64, r: f64) -> Option<(f64, f64, f64)> {
+    let (m, a, q2) = (mass, spin * mass, (charge * mass).powi(2));
+
+    let w = (2.0*m*r - q2) / r.powi(2);
+    let (g_tt, g_tphi, g_phiphi) = (w - 1.0, -w*a, r.powi(2) + a.powi(2) + w*a.powi(2));
+    let omega = 1.0 / (a + r.powi(2) / (m*r - q2).sqrt());
+
+    let norm = -(g_tt + 2.0*omega*g_tphi + omega.powi(2)*g_phiphi);
+    if norm > 0.0 {
+        let u_t = 1.0 / norm.sqrt();
+        Some((-(g_tt + omega*g_tphi) * u_t, (g_tphi + omega*g_phiphi) * u_t, omega))
+    } else {
+        None
+    }
+}
+
+// Radius of the innermost stable circular orbit around a Kerr-Newman hole,
+// prograde for positive `spin`. There it is where the energy of circular
+// orbits is the lowest, found by a scan down from 10 M.
+pub fn kerr_newman_isco(mass: f64, spin: f64, charge: f64) -> f64 {
+    let m = mass;
+    let energy = |r: f64| kerr_newman_orbit(mass, spin, charge, r).map_or(f64::INFINITY, |(e, _, _)| e);
+
+    let steps = 10_000;
+    let h = 10.0 * m / steps as f64;
+    let mut r = 10.0 * m;
+    while r > h && energy(r - h) < energy(r) {
+        r -= h;
+    }
+
+    // Golden section around the lowest sample
+    let ratio = (5f64.sqrt() - 1.0) / 2.0;
+    let (mut lo, mut hi) = (r - h, r + h);
+    for _ in 0..60 {
+        let (r1, r2) = (hi - ratio*(hi - lo), lo + ratio*(hi - lo));
+        if energy(r1) < energy(r2) {
+            hi = r2;
+        } else {
+            lo = r1;
+        }
+    }
+    0.5 * (lo + hi)
+}
+
+
+// The original ad hoc profile, T = 7000 K (r/r_s)^(-3/4) between 3 and 5 r_s
+#[derive(Clone, Copy, Debug)]
+pub struct SimpleDisk {
+    pub mass: f64,
+}
+
+impl SimpleDisk {
+    pub fn new(mass: f64) -> SimpleDisk {
+        SimpleDisk {mass}
+    }
+}
+
+impl Disk for SimpleDisk {
+    fn inner_radius(&self) -> f64 {
+        6.0 * self.mass
+    }
+
+    fn outer_radius(&self) -> f64 {
+        10.0 * self.mass
+    }
+
+    fn temperature(&self, r: f64) -> f64 {
+        7e3 * (r / (2.0 * self.mass)).powf(-3.0/4.0)
+    }
+}
+
+
+// Relativistic thin disk of Novikov & Thorne, radiating the Page-Thorne flux
+// as a blackbody from the ISCO of a Kerr-Newman hole outwards
+#[derive(Clone, Copy, Debug)]
+pub struct NovikovThorne {
+    pub mass: f64, // geometric, in scene units
+    pub spin: f64,
+    pub charge: f64,
+    pub solar_masses: f64, // physical mass of the hole
+    pub accretion_rate: f64, // in solar masses per year
+    pub inner: f64,
+    pub outer: f64,
+}
+
+impl NovikovThorne {
+    pub fn new(mass: f64, spin: f64, charge: f64, solar_masses: f64, accretion_rate: f64, outer: f64) -> NovikovThorne {
+        let inner = if charge == 0.0 { isco(mass, spin) } else { kerr_newman_isco(mass, spin, charge) };
+        NovikovThorne {mass, spin, charge, solar_masses, accretion_rate, inner, outer}
+    }
+
+    // Energy radiated by each face of the disk at `r`, in W/m^2
+    pub fn flux(&self, r: f64) -> f64 {
+        if r <= self.inner_radius() {
+            return 0.0;
+        }
+
+        let accretion_rate = self.accretion_rate * SOLAR_MASS / YEAR;
+        let mass = self.solar_masses * SOLAR_MASS;
+        let scale = accretion_rate*C.powi(6)/(G.powi(2)*mass.powi(2));
+
+        let flux = if self.charge == 0.0 { self.kerr_flux(r) } else { self.page_thorne_flux(r) };
+        scale * flux
+    }
+
+    // Flux in units of Mdot c^6/(G^2 M^2), in closed form around a Kerr hole
+    fn kerr_flux(&self, r: f64) -> f64 {
+        let a = self.spin;
+        let x = (r/self.mass).sqrt();
+        let x0 = (self.inner_radius()/self.mass).sqrt();
+
+        // Roots of x^3 - 3x + 2a
+        let roots = [
+            2.0*((a.acos() - PI)/3.0).cos(),
+            2.0*((a.acos() + PI)/3.0).cos(),
+            -2.0*(a.acos()/3.0).cos(),
+        ];
+
+        let mut s = x - x0 - 1.5*a*(x/x0).ln();
+        for i in 0..3 {
+            let (xi, xj, xk) = (roots[i], roots[(i + 1) % 3], roots[(i + 2) % 3]);
+            // Vanishes as a -> 0, where the root itself goes to 0
+            if xi.abs() < 1e-10 {
+                continue;
+            }
+            s -= 3.0*(xi - a).powi(2)/(xi*(xi - xj)*(xi - xk))*((x - xi)/(x0 - xi)).ln();
+        }
+
+        3.0/(8.0*PI) * s/(x.powi(4)*(x.powi(3) - 3.0*x + 2.0*a))
+    }
+
+    // Flux in units of Mdot c^6/(G^2 M^2) from the Page-Thorne integral over
+    // the circular orbits of any hole, in units of M:
+    // F = -Ω'/(4π r (E - ΩL)^2) ∫ (E - ΩL) L' dr from the inner edge
+    fn page_thorne_flux(&self, r: f64) -> f64 {
+        let orbit = |r: f64| kerr_newman_orbit(1.0, self.spin, self.charge, r).unwrap_or((f64::NAN, f64::NAN, f64::NAN));
+        let derivative = |f: &dyn Fn(f64) -> f64, r: f64| {
+            let h = 1e-5 * r;
+            (f(r + h) - f(r - h)) / (2.0*h)
+        };
+        let (r, r_in) = (r/self.mass, self.inner_radius()/self.mass);
+
+        // Simpson's rule in ln r, which follows both the edge and far away
+        let integrand = |s: f64| {
+            let r = r_in * s.exp();
+            let (e, l, omega) = orbit(r);
+            (e - omega*l) * derivative(&|r| orbit(r).1, r) * r
+        };
+        let steps = 100;
+        let h = (r/r_in).ln() / steps as f64;
+        let integral = (0..=steps).map(|i| {
+            let weight = if i == 0 || i == steps { 1.0 } else if i % 2 == 1 { 4.0 } else { 2.0 };
+            weight * integrand(i as f64 * h)
+        }).sum::<f64>() * h/3.0;
+
+        let (e, l, omega) = orbit(r);
+        -derivative(&|r| orbit(r).2, r) / (4.0*PI * r * (e - omega*l).powi(2)) * integral
+    }
+
+    // Newtonian flux 3 G M Mdot / (8 pi r^3), for comparison
+    pub fn newtonian_flux(&self, r: f64) -> f64 {
+        let accretion_rate = self.accretion_rate * SOLAR_MASS / YEAR;
+        let r = units::geometric_to_km(r, self.solar_masses, self.mass) * 1e3;
+
+        3.0*G*self.solar_masses*SOLAR_MASS*accretion_rate/(8.0*PI*r.powi(3))
+    }
+}
+
+impl Disk for NovikovThorne {
+    fn inner_radius(&self) -> f64 {
+        self.inner
+    }
+
+    fn outer_radius(&self) -> f64 {
+        self.outer
+    }
+
+    fn temperature(&self, r: f64) -> f64 {
+        (self.flux(r)/STEFAN_BOLTZMANN).powf(0.25)
+    }
+}
+
+
+// Any of the disks above, chosen at runtime
+#[derive(Clone, Copy, Debug)]
+pub enum AnyDisk {
+    None, // nothing orbits, e.g. a wormhole
+    Simple(SimpleDisk),
+    NovikovThorne(NovikovThorne),
+}
+
+impl AnyDisk {
+    // Names as accepted by the CLI. The Novikov-Thorne disk extends to 20 M.
+    pub fn from_name(name: &str, mass: f64, spin: f64, charge: f64, solar_masses: f64, accretion_rate: f64) -> Option<AnyDisk> {
+        match name {
+            "none" => Some(AnyDisk::None),
+            "simple" => Some(AnyDisk::Simple(SimpleDisk::new(mass))),
+            "novikov-thorne" => Some(AnyDisk::NovikovThorne(
+                NovikovThorne::new(mass, spin, charge, solar_masses, accretion_rate, 20.0 * mass)
+            )),
+            _ => None,
+        }
+    }
+
+    // Same disk around a hole of another spin and charge
+    pub fn set_hole(&mut self, spin: f64, charge: f64) {
+        if let Self::NovikovThorne(d) = self {
+            *d = NovikovThorne::new(d.mass, spin, charge, d.solar_masses, d.accretion_rate, d.outer);
+        }
+    }
+}
+
+impl Disk for AnyDisk {
+    fn inner_radius(&self) -> f64 {
+        match self {
+            Self::None => 0.0,
+            Self::Simple(d) => d.inner_radius(),
+            Self::NovikovThorne(d) => d.inner_radius(),
+        }
+    }
+
+    fn outer_radius(&self) -> f64 {
+        match self {
+            Self::None => 0.0,
+            Self::Simple(d) => d.outer_radius(),
+            Self::NovikovThorne(d) => d.outer_radius(),
+        }
+    }
+
+    fn temperature(&self, r: f64) -> f64 {
+        match self {
+            Self::None => 0.0,
+            Self::Simple(d) => d.temperature(r),
+            Self::NovikovThorne(d) => d.temperature(r),
+        }
+    }
+
+    // Nothing to expose without a disk
+    fn exposure(&self) -> f64 {
+        match self {
+            Self::None => 1.0,
+            Self::Simple(d) => d.exposure(),
+            Self::NovikovThorne(d) => d.exposure(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use rand::prelude::*;
+
+    #[test]
+    fn isco_limits() {
+        assert!((isco(0.5, 0.0) - 3.0).abs() < 1e-12);
+        assert!((isco(0.5, 1.0) - 0.5).abs() < 1e-12);
+        assert!((isco(0.5, -1.0) - 4.5).abs() < 1e-12);
+    }
+
+    #[test]
+    fn kerr_newman_isco_limits() {
+        let spin = 1.8 * random::<f64>() - 0.9;
+        let expected = isco(0.5, spin);
+        assert!((kerr_newman_isco(0.5, spin, 0.0) - expected).abs() < 1e-6, "Failed at {}", spin);
+
+        // 4M for an extremal Reissner-Nordström hole
+        assert!((kerr_newman_isco(0.5, 0.0, 1.0) - 2.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn novikov_thorne_flux() {
+        let spin = 2.0 * random::<f64>() - 1.0;
+        for &spin in &[0.0, spin] {
+            let disk = NovikovThorne::new(0.5, spin, 0.0, 1e8, 1.0, 1e7);
+
+            // Zero torque at the inner edge
+            let r_in = disk.inner_radius();
+            assert_eq!(disk.flux(r_in), 0.0);
+            assert!(disk.flux(1.01 * r_in) > 0.0);
+
+            // Newtonian far away, up to the inner boundary term
+            let r = 1e10;
+            let expected = disk.newtonian_flux(r) * (1.0 - (r_in/r).sqrt());
+            assert!((disk.flux(r)/expected - 1.0).abs() < 1e-3, "Failed at spin {}", spin);
+        }
+    }
+
+    #[test]
+    fn page_thorne_integral_matches_kerr() {
+        let disk = NovikovThorne::new(0.5, 1.8 * random::<f64>() - 0.9, 0.0, 6.5e9, 0.01, 10.0);
+        for &k in &[1.1, 2.0, 10.0] {
+            let r = k * disk.inner_radius();
+            let expected = disk.kerr_flux(r);
+            assert!((disk.page_thorne_flux(r)/expected - 1.0).abs() < 1e-4, "Failed at {:?}", (disk.spin, k));
+        }
+    }
+
+    #[test]
+    fn charged_flux_is_positive() {
+        for &(spin, charge) in &[(0.6, 0.8), (0.9, 0.3), (0.0, 0.99), (-0.5, 0.5)] {
+            let disk = NovikovThorne::new(0.5, spin, charge, 6.5e9, 0.01, 10.0);
+
+            let r_in = disk.inner_radius();
+            for &k in &[1.01, 1.2, 2.0] {
+                assert!(disk.flux(k * r_in) > 0.0, "Failed at {:?}", (spin, charge, k));
+            }
+            assert!(disk.exposure().is_finite(), "Failed at {:?}", (spin, charge));
+        }
+    }
+}
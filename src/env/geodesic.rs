@@ -0,0 +1,310 @@
+use std::sync::Arc;
+
+use nalgebra as na;
+use na::{Vector3, Vector4, Unit};
+
+use crate::physics::*;
+use crate::metric::{Metric, Observer, Schwarzschild, Kerr, PainleveGullstrand, ReissnerNordstrom, KerrNewman, MorrisThorne, SchwarzschildDeSitter, Hayward, Bardeen};
+use crate::integrator::{self, Integrator, AnyIntegrator};
+use crate::disk::{Disk, AnyDisk};
+use crate::lensing::{LensingMap, End, Hit};
+use crate::{binet, analytic};
+
+use super::*;
+
+pub type SchwarzschildRaytracing = GeodesicRaytracing<Schwarzschild>;
+pub type KerrRaytracing = GeodesicRaytracing<Kerr>;
+pub type PainleveRaytracing = GeodesicRaytracing<PainleveGullstrand>;
+pub type ReissnerNordstromRaytracing = GeodesicRaytracing<ReissnerNordstrom>;
+pub type KerrNewmanRaytracing = GeodesicRaytracing<KerrNewman>;
+pub type WormholeRaytracing = GeodesicRaytracing<MorrisThorne>;
+pub type DeSitterRaytracing = GeodesicRaytracing<SchwarzschildDeSitter>;
+pub type HaywardRaytracing = GeodesicRaytracing<Hayward>;
+pub type BardeenRaytracing = GeodesicRaytracing<Bardeen>;
+
+// Orbits in the lensing map, enough for the photon ring to stay sharp
+const LENSING_SAMPLES: usize = 4096;
+
+// How rays are followed from the camera
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Tracer {
+    Geodesic, // integrates every ray
+    LensingMap, // looks rays up in a LensingMap, spherically symmetric metrics only
+    Binet, // integrates Binet's equation on the orbital plane, Schwarzschild only
+    Analytic, // exact orbits from elliptic integrals, Schwarzschild only
+}
+
+impl Tracer {
+    // Names as accepted by the CLI
+    pub fn from_name(name: &str) -> Option<Tracer> {
+        match name {
+            "geodesic" => Some(Tracer::Geodesic),
+            "lensing-map" => Some(Tracer::LensingMap),
+            "binet" => Some(Tracer::Binet),
+            "analytic" => Some(Tracer::Analytic),
+            _ => None,
+        }
+    }
+}
+
+// Traces null geodesics of any `Metric` backwards from the camera
+#[derive(Clone)]
+pub struct GeodesicRaytracing<M, I = AnyIntegrator> where
+    M: Metric,
+    I: Integrator,
+{
+    metric: M,
+    integrator: I,
+    disk: AnyDisk,
+    exposure: f64,
+    pos: Vector3<f64>,
+    dir: Unit<Vector3<f64>>,
+    up: Unit<Vector3<f64>>,
+    #[allow(dead_code)]
+    near: f64,
+    fovy: f64,
+    aspect: f64, // x/y
+    skydome: Option<Box<image::RgbImage>>,
+    far_skydome: Option<Box<image::RgbImage>>, // seen through a wormhole
+    observer: Observer,
+    tracer: Tracer,
+    lensing: Option<Arc<LensingMap>>, // shared by the clones of every render
+}
+
+impl<M, I> GeodesicRaytracing<M, I> where
+    M: Metric,
+    I: Integrator,
+{
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(metric: M, integrator: I, disk: AnyDisk, pos: Vector3<f64>, dir: Vector3<f64>, up:Vector3<f64>, near: f64, fovy: f64, aspect: f64, skydome: Option<Box<image::RgbImage>>) -> GeodesicRaytracing<M, I> {
+        let up = Unit::new_normalize((dir.cross(&up)).cross(&dir));
+        let dir = Unit::new_normalize(dir);
+        GeodesicRaytracing {metric, integrator, disk, exposure: disk.exposure(), pos, dir, up, near, fovy, aspect, skydome, far_skydome: None, observer: Observer::Static, tracer: Tracer::Geodesic, lensing: None}
+    }
+
+    pub fn new_orbiting(metric: M, integrator: I, disk: AnyDisk, pos: Vector3<f64>, aspect: f64, skydome: Option<Box<image::RgbImage>>) -> GeodesicRaytracing<M, I> {
+        GeodesicRaytracing::new(
+            metric,
+            integrator,
+            disk,
+            pos,
+            -pos,
+            *Vector3::z_axis(),
+            0.1,
+            std::f64::consts::PI/3.0,
+            aspect,
+            skydome,
+        )
+    }
+
+    pub fn new_orbiting_spherical(metric: M, integrator: I, disk: AnyDisk, (r, theta, phi): (f64, f64, f64), aspect: f64, skydome: Option<Box<image::RgbImage>>) -> GeodesicRaytracing<M, I> {
+        let pos = Vector3::new(
+            r * theta.sin() * phi.cos(),
+            r * theta.sin() * phi.sin(),
+            r * theta.cos(),
+        );
+
+        GeodesicRaytracing::new_orbiting(metric, integrator, disk, pos, aspect, skydome)
+    }
+
+    // Sky on the other side of a wormhole, where r is negative
+    pub fn set_far_skydome(&mut self, far_skydome: Option<Box<image::RgbImage>>) {
+        self.far_skydome = far_skydome;
+    }
+
+    // Moves the camera along with `observer`, which changes the image by
+    // aberration and the Doppler shift of the camera
+    pub fn set_observer(&mut self, observer: Observer) {
+        self.observer = observer;
+    }
+
+    // Photon energy measured by disk matter on a Keplerian orbit of radius
+    // `r`, for a ray with covariant momentum `p`
+    fn emitted_energy(&self, r: f64, p: &Vector4<f64>) -> f64 {
+        let omega = self.metric.keplerian_omega(r);
+        let g = self.metric.g(&Vector4::new(0.0, r, std::f64::consts::FRAC_PI_2, 0.0));
+        let u_t = 1.0/(-(g[(0, 0)] + 2.0*omega*g[(0, 3)] + omega.powi(2)*g[(3, 3)])).sqrt();
+
+        u_t * (p[0] + omega*p[3])
+    }
+
+    fn build_lensing_map(&self) -> Arc<LensingMap> {
+        Arc::new(LensingMap::new(&self.metric, &self.integrator, self.pos.norm(), LENSING_SAMPLES))
+    }
+
+    // Follows the cartesian `dir` on the plane spanned by it and the
+    // direction to the hole, where ψ is the angle from the camera position
+    fn raytrace_in_plane(&self, dir: &Vector3<f64>, p: &Vector4<f64>, observed: f64) -> Radiance {
+        let e1 = self.pos.normalize();
+        let cos_alpha = (-dir.dot(&e1)).clamp(-1.0, 1.0);
+        let perp = dir + e1 * cos_alpha;
+        let e2 = if perp.norm() > 1e-12 {
+            perp.normalize()
+        } else {
+            // Radial, any plane through it will do
+            e1.cross(&Vector3::z_axis()).try_normalize(1e-12).unwrap_or_else(Vector3::x)
+        };
+
+        // First ψ at which the plane meets the equator, none if it is the equator
+        let crossing = if e1.z != 0.0 || e2.z != 0.0 {
+            Some((-e1.z).atan2(e2.z).rem_euclid(std::f64::consts::PI))
+        } else {
+            None
+        };
+
+        let (alpha, r, mass) = (cos_alpha.acos(), self.pos.norm(), self.metric.mass());
+        // Only Schwarzschild is traced on a plane, where r is the radius
+        let contains = |r| self.disk.contains(r);
+        let binet = || binet::trace(mass, r, alpha, (2.0*r).max(60.0*mass), crossing, contains);
+        let hit = match (self.tracer, &self.lensing) {
+            (Tracer::LensingMap, Some(lensing)) => lensing.path(alpha).hit(crossing, contains),
+            // Radial rays and rays inside the photon sphere are left to Binet
+            (Tracer::Analytic, _) => analytic::Orbit::new(mass, r, alpha)
+                .map_or_else(binet, |orbit| orbit.hit(crossing, contains)),
+            _ => binet(),
+        };
+
+        match hit {
+            Hit::Disk(r) => {
+                let redshift = observed / self.emitted_energy(r, p);
+                (self.disk.radiance(r, redshift) * self.exposure).cast()
+            },
+            Hit::End(End::Escaped(beta)) => sky_radiance(&self.skydome, &(e1 * beta.cos() + e2 * beta.sin())),
+            Hit::End(End::Captured) => Radiance::zeros(),
+        }
+    }
+}
+
+impl<I> GeodesicRaytracing<Schwarzschild, I> where
+    I: Integrator,
+{
+    // Schwarzschild geodesics stay on a plane through the hole, so they can be
+    // followed there or looked up in a lensing map rebuilt only when the
+    // camera radius changes
+    pub fn set_tracer(&mut self, tracer: Tracer) {
+        self.tracer = tracer;
+        self.lensing = match tracer {
+            Tracer::LensingMap => Some(self.build_lensing_map()),
+            _ => None,
+        };
+    }
+}
+
+impl<M, I> Environment for GeodesicRaytracing<M, I> where
+    M: Metric,
+    I: Integrator,
+{
+    fn raytrace(&self, canvas: (f64,f64)) -> Radiance {
+        // Find direction
+        let pixel_dir = get_pixel_dir(canvas, self.fovy, self.aspect, &self.dir, &self.up);
+
+        // Convert coords. Far from the hole the coordinates are approximately
+        // spherical, so the axes of the camera are taken from there, in the
+        // rest frame of its observer.
+        let pos = vec3to4(&cart2sph(&self.pos));
+        let n = cart2sph_at(&vec4to3(&pos), &pixel_dir);
+        let u = self.observer.velocity(&self.metric, &pos);
+        let e = self.metric.tetrad(&pos, &u);
+
+        // Traced back in time along the light reaching the camera, which only
+        // makes a difference in spacetimes that aren't time symmetric
+        let dir = -e[0] + e[1]*n[0] + e[2]*n[1] + e[3]*n[2];
+
+        // Photon energy measured by the camera, and the conserved p_t and p_phi
+        // used to get the energy measured by the emitter at the other end
+        let p = self.metric.g(&pos) * dir;
+        let observed = p.dot(&u);
+
+        // The plane tracers take the direction of the ray seen from rest
+        if self.tracer != Tracer::Geodesic {
+            let e = self.metric.tetrad(&pos, &self.metric.static_observer(&pos));
+            let n = Vector3::new(p.dot(&e[1]), p.dot(&e[2]), p.dot(&e[3])) / p.dot(&e[0]);
+            return self.raytrace_in_plane(&sph2cart_at(&vec4to3(&pos), &n), &p, observed);
+        }
+
+        let horizon = self.metric.horizon();
+        let rs = 2.0 * self.metric.length_scale();
+        let r_escape = (2.0*self.metric.radius(&pos)).max(30.0*rs).min(self.metric.sky_radius());
+
+        // Integrate
+        let mut dt = self.integrator.base_step() / rs * self.metric.radius(&pos).powf(2.0);
+        let mut y = integrator::state(&pos, &dir);
+        let max_steps = 1_000_000;
+        for _ in 0..max_steps {
+            let (pos, dir) = (integrator::pos(&y), integrator::dir(&y));
+
+            for lambda in 0..4 {
+                if pos[lambda].is_nan() || dir[lambda].is_nan() {
+                    return Radiance::new(1.0, 0.0, 0.0);
+                }
+            }
+
+            // Out to the sky, on the far side of a wormhole if r went negative.
+            // The direction is measured in proper lengths, which only matters
+            // when the sky isn't far away.
+            let (r, side) = (self.metric.radius(&pos), pos[1].signum());
+            if r > r_escape && side * dir[1] > 0.0 {
+                let v = Vector3::new(
+                    side * dir[1] * self.metric.g(&pos)[(1, 1)].sqrt(),
+                    r * dir[2],
+                    r * pos[2].sin() * dir[3],
+                );
+                let skydome = if side > 0.0 { &self.skydome } else { &self.far_skydome };
+                return sky_radiance(skydome, &sph2cart_at(&vec4to3(&pos), &v));
+            }
+
+            // Event horizon. Rays traced back from outside only creep towards
+            // it, and so do some of those traced back from inside while the
+            // others cross it outwards. A wormhole has none.
+            let creeping = (pos[1] - horizon).abs() < 0.01 * horizon && dir[1].abs() < 0.1 * dir[0].abs();
+            if horizon > 0.0 && (creeping || (pos[1] < 1.01 * horizon && dir[1] < 0.0)) {
+                return Radiance::zeros();
+            }
+
+            dt = integrator::ray_step(&self.integrator, &self.metric, &pos, &dir, dt);
+            let (taken, next) = self.integrator.step(&self.metric, &mut y, dt);
+            dt = next;
+
+            if taken > 0.0 {
+                let (new_pos, mut new_dir) = (integrator::pos(&y), integrator::dir(&y));
+
+                // Accretion disk, on the equatorial plane. Rays are followed
+                // through every crossing, so the images that wrap around the
+                // hole show up as well.
+                let (z, new_z) = (r * pos[2].cos(), self.metric.radius(&new_pos) * new_pos[2].cos());
+                if z * new_z <= 0.0 && z != new_z {
+                    let frac = z / (z - new_z);
+                    let hit = pos + (new_pos - pos) * frac;
+                    let hit_r = self.metric.radius(&hit);
+                    if self.disk.contains(hit_r) {
+                        let redshift = observed / self.emitted_energy(hit[1], &p);
+                        return (self.disk.radiance(hit_r, redshift) * self.exposure).cast();
+                    }
+                }
+
+                // Keep the ray null, drift in the constraint grows with every step
+                self.metric.null_norm(&new_pos, &mut new_dir);
+                y = integrator::state(&new_pos, &new_dir);
+            }
+        }
+
+        Radiance::zeros()
+    }
+
+    fn get_data(&self) -> (Vector3<f64>, Unit<Vector3<f64>>, Unit<Vector3<f64>>){
+        (self.pos, self.dir, self.up)
+    }
+
+    fn set_data(&mut self, pos: &Vector3<f64>, dir: &Vector3<f64>, up: &Vector3<f64>) {
+        self.pos = *pos;
+        self.dir = Unit::new_normalize(*dir);
+        self.up = Unit::new_normalize(dir.cross(up).cross(dir));
+
+        // Rotations keep the map, only moving in or out needs a new one
+        if let Some(lensing) = &self.lensing {
+            if (lensing.r_cam - pos.norm()).abs() > 1e-9 * lensing.r_cam {
+                self.lensing = Some(self.build_lensing_map());
+            }
+        }
+    }
+}
@@ -1,20 +1,23 @@
-use sdl2::pixels::Color;
+use image::Pixel;
 
 use nalgebra::{Vector3, Unit};
 
 use crate::physics::*;
+use crate::hdr::{self, Radiance};
 
 
 mod euclid;
 pub use euclid::*;
 
-mod schwarzschild;
-pub use schwarzschild::*;
+mod geodesic;
+pub use geodesic::*;
 
 
 pub trait Environment: Clone + Send + Sync + 'static {
     // === Needed ==
-    fn raytrace(&self, canvas_pos: (f64,f64)) -> Color;
+    // Linear radiance seen through `canvas_pos`, in units where the hottest
+    // part of the accretion disk has a luminance of 1
+    fn raytrace(&self, canvas_pos: (f64,f64)) -> Radiance;
     
     fn get_data(&self) -> (Vector3<f64>, Unit<Vector3<f64>>, Unit<Vector3<f64>>); // pos, dir, up
 
@@ -36,19 +39,19 @@ pub trait Environment: Clone + Send + Sync + 'static {
 
 
     fn set_pos(&mut self, pos: &Vector3<f64>) {
-        self.set_data(&pos, &self.dir(), &self.up())
+        self.set_data(pos, &self.dir(), &self.up())
     }
     
     fn set_dir(&mut self, dir: &Vector3<f64>) {
-        self.set_data(&self.pos(), &dir, &self.up())
+        self.set_data(&self.pos(), dir, &self.up())
     }
     
     fn set_up(&mut self, up: &Vector3<f64>) {
-        self.set_data(&self.pos(), &self.dir(), &up)
+        self.set_data(&self.pos(), &self.dir(), up)
     }
     
     fn set_pos_orbiting(&mut self, pos: &Vector3<f64>) {
-        let pos = sph2cart(&pos);
+        let pos = sph2cart(pos);
         self.set_pos(&pos);
 
         let dir = -pos;
@@ -57,10 +60,12 @@ pub trait Environment: Clone + Send + Sync + 'static {
         self.set_dir(&dir);
     }
 
-    fn render_pixel(&self, x: u32, y: u32, screen: [u32; 2]) -> Color {
-        let x = x as f64 + 0.5;
-        let y = y as f64 + 0.5;
+    fn render_pixel(&self, x: u32, y: u32, screen: [u32; 2]) -> Radiance {
+        self.render_sample(x as f64 + 0.5, y as f64 + 0.5, screen)
+    }
 
+    // Ray through the continuous screen position (x, y), in pixels
+    fn render_sample(&self, x: f64, y: f64, screen: [u32; 2]) -> Radiance {
         let sw = screen[0] as f64;
         let sh = screen[1] as f64;
 
@@ -71,6 +76,39 @@ pub trait Environment: Clone + Send + Sync + 'static {
     }
 }
 
+// Radiance of the sky seen in the cartesian direction `dir`
+pub fn sky_radiance(skydome: &Option<Box<image::RgbImage>>, dir: &Vector3<f64>) -> Radiance {
+    let mut theta = ((dir.x.powf(2.0) + dir.y.powf(2.0)).sqrt()).atan2(dir.z);
+    if theta < 0.0 {
+        theta += std::f64::consts::TAU;
+    }
+
+    let mut phi = dir.y.atan2(dir.x);
+    if phi < 0.0 {
+        phi += std::f64::consts::TAU;
+    }
+
+    match skydome {
+        Some(skydome) => {
+            let (w, h) = skydome.dimensions();
+
+            let x = ((phi / std::f64::consts::TAU * (w as f64)) as u32).min(w - 1);
+            let y = ((theta / std::f64::consts::PI * (h as f64)) as u32).min(h - 1);
+
+            let pixel = skydome.get_pixel(x, y).channels();
+            hdr::from_srgb8([pixel[0], pixel[1], pixel[2]])
+        },
+        None => {
+            if ((phi / std::f64::consts::TAU * 100.0).fract() < 0.25)
+             || ((theta / std::f64::consts::PI * 50.0).fract() < 0.25) {
+                Radiance::new(1.0, 0.0, 0.0)
+            } else {
+                Radiance::new(0.0, 0.0, 1.0)
+            }
+        },
+    }
+}
+
 #[cfg(test)]
 mod tests {
 
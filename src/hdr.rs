@@ -0,0 +1,151 @@
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+use nalgebra::Vector3;
+
+use image::codecs::hdr::HdrEncoder;
+
+
+// Linear sRGB radiance, unbounded
+pub type Radiance = Vector3<f32>;
+
+// Linear radiance of an 8 bit sRGB color, e.g. a skydome pixel
+pub fn from_srgb8(rgb: [u8; 3]) -> Radiance {
+    let decode = |c: u8| {
+        let c = c as f32 / 255.0;
+        if c <= 0.040_45 {
+            c / 12.92
+        } else {
+            ((c + 0.055) / 1.055).powf(2.4)
+        }
+    };
+
+    Radiance::new(decode(rgb[0]), decode(rgb[1]), decode(rgb[2]))
+}
+
+// Whether `path` names a floating point image format
+pub fn is_hdr_path(path: &str) -> bool {
+    matches!(extension(path).as_deref(), Some("hdr") | Some("exr"))
+}
+
+// Saves the raw radiance, row major, as Radiance .hdr or OpenEXR depending
+// on the extension of `path`
+pub fn save(path: &str, width: u32, height: u32, pixels: &[Radiance]) -> image::ImageResult<()> {
+    let file = BufWriter::new(File::create(path)?);
+
+    match extension(path).as_deref() {
+        Some("exr") => write_exr(file, width, height, pixels)?,
+        _ => {
+            let pixels: Vec<image::Rgb<f32>> = pixels.iter().map(|p| image::Rgb([p[0], p[1], p[2]])).collect();
+            HdrEncoder::new(file).encode(&pixels, width as usize, height as usize)?
+        },
+    };
+
+    Ok(())
+}
+
+fn extension(path: &str) -> Option<String> {
+    Path::new(path).extension().map(|ext| ext.to_string_lossy().to_lowercase())
+}
+
+// Minimal single part, scanline, uncompressed OpenEXR with 32 bit float
+// R, G and B channels
+pub fn write_exr<W: Write>(mut w: W, width: u32, height: u32, pixels: &[Radiance]) -> io::Result<()> {
+    fn attribute(header: &mut Vec<u8>, name: &str, kind: &str, value: &[u8]) {
+        header.extend_from_slice(name.as_bytes());
+        header.push(0);
+        header.extend_from_slice(kind.as_bytes());
+        header.push(0);
+        header.extend_from_slice(&(value.len() as i32).to_le_bytes());
+        header.extend_from_slice(value);
+    }
+
+    // Channels are stored in alphabetical order
+    let channels = [("B", 2), ("G", 1), ("R", 0)];
+
+    let mut chlist = Vec::new();
+    for (name, _) in channels.iter() {
+        chlist.extend_from_slice(name.as_bytes());
+        chlist.push(0);
+        chlist.extend_from_slice(&2i32.to_le_bytes()); // FLOAT
+        chlist.extend_from_slice(&[0, 0, 0, 0]); // pLinear and reserved
+        chlist.extend_from_slice(&1i32.to_le_bytes()); // x sampling
+        chlist.extend_from_slice(&1i32.to_le_bytes()); // y sampling
+    }
+    chlist.push(0);
+
+    let mut window = Vec::new();
+    for v in &[0i32, 0, width as i32 - 1, height as i32 - 1] {
+        window.extend_from_slice(&v.to_le_bytes());
+    }
+
+    let mut header = vec![0x76, 0x2f, 0x31, 0x01, 2, 0, 0, 0];
+    attribute(&mut header, "channels", "chlist", &chlist);
+    attribute(&mut header, "compression", "compression", &[0]);
+    attribute(&mut header, "dataWindow", "box2i", &window);
+    attribute(&mut header, "displayWindow", "box2i", &window);
+    attribute(&mut header, "lineOrder", "lineOrder", &[0]);
+    attribute(&mut header, "pixelAspectRatio", "float", &1f32.to_le_bytes());
+    attribute(&mut header, "screenWindowCenter", "v2f", &[0; 8]);
+    attribute(&mut header, "screenWindowWidth", "float", &1f32.to_le_bytes());
+    header.push(0);
+    w.write_all(&header)?;
+
+    // One scanline per chunk: y, size, then each channel in turn
+    let line_size = 4 * 3 * width as usize;
+    let chunk_size = 8 + line_size;
+    let first_chunk = header.len() + 8 * height as usize;
+    for y in 0..height as usize {
+        w.write_all(&((first_chunk + y * chunk_size) as u64).to_le_bytes())?;
+    }
+
+    for (y, line) in pixels.chunks(width as usize).enumerate() {
+        w.write_all(&(y as i32).to_le_bytes())?;
+        w.write_all(&(line_size as i32).to_le_bytes())?;
+        for (_, c) in channels.iter() {
+            for pixel in line {
+                w.write_all(&pixel[*c].to_le_bytes())?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use rand::prelude::*;
+
+    #[test]
+    fn srgb_round_trip() {
+        for &c in &[0u8, 10, 128, 255] {
+            let encoded = crate::tonemap::ToneMapping::default().apply(&from_srgb8([c, c, c]));
+            assert_eq!(encoded.r, c);
+        }
+    }
+
+    #[test]
+    fn exr_layout() {
+        let (width, height) = (3, 2);
+        let pixels: Vec<Radiance> = (0..width*height).map(|_| Radiance::new(random(), random(), random())).collect();
+
+        let mut buffer = Vec::new();
+        write_exr(&mut buffer, width, height, &pixels).unwrap();
+        assert_eq!(&buffer[..4], &[0x76, 0x2f, 0x31, 0x01]);
+
+        // Last float is the red channel of the last pixel
+        let last = f32::from_le_bytes([buffer[buffer.len() - 4], buffer[buffer.len() - 3], buffer[buffer.len() - 2], buffer[buffer.len() - 1]]);
+        assert_eq!(last, pixels[pixels.len() - 1][0]);
+
+        // Offsets point at the chunks
+        let header_len = buffer.len() - 8 * height as usize - height as usize * (8 + 12 * width as usize);
+        let offset = u64::from_le_bytes([
+            buffer[header_len], buffer[header_len + 1], buffer[header_len + 2], buffer[header_len + 3],
+            buffer[header_len + 4], buffer[header_len + 5], buffer[header_len + 6], buffer[header_len + 7],
+        ]) as usize;
+        assert_eq!(offset, header_len + 8 * height as usize);
+    }
+}
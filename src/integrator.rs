@@ -0,0 +1,363 @@
+use nalgebra::{SVector, Vector3, Vector4};
+
+use crate::metric::Metric;
+
+
+// Position and velocity along a geodesic, (x^μ, dx^μ/dt)
+pub type State = SVector<f64, 8>;
+
+pub fn state(pos: &Vector4<f64>, dir: &Vector4<f64>) -> State {
+    State::from_fn(|i, _| if i < 4 { pos[i] } else { dir[i - 4] })
+}
+
+pub fn pos(y: &State) -> Vector4<f64> {
+    Vector4::new(y[0], y[1], y[2], y[3])
+}
+
+pub fn dir(y: &State) -> Vector4<f64> {
+    Vector4::new(y[4], y[5], y[6], y[7])
+}
+
+// Right hand side of the geodesic equation as a first order system
+pub fn derivative<M: Metric>(metric: &M, y: &State) -> State {
+    let (pos, dir) = (pos(y), dir(y));
+    state(&dir, &metric.geodesic_acc(&pos, &dir))
+}
+
+// Step to try for a ray at `pos` moving with `dir`, given the step `dt` the
+// integrator asked for. Fixed step integrators grow it as r^2, and no step
+// goes more than half the way to the horizon.
+pub fn ray_step<M: Metric, I: Integrator>(integrator: &I, metric: &M, pos: &Vector4<f64>, dir: &Vector4<f64>, dt: f64) -> f64 {
+    let (rs, horizon, r) = (2.0 * metric.length_scale(), metric.horizon(), metric.radius(pos));
+    let dt = if integrator.is_adaptive() {
+        dt
+    } else {
+        integrator.base_step() / rs * r.powf(2.0)
+    };
+
+    let speed = Vector3::new(dir[1], r * dir[2], r * pos[2].sin() * dir[3]).norm();
+    dt.min(0.5 * (r - horizon).max(0.01 * horizon) / speed).max(1e-9)
+}
+
+
+pub trait Integrator: Clone + Send + Sync + 'static {
+    // === Needed ==
+    // Tries to advance `y` by `dt`. Returns the step actually taken (0 if it
+    // was rejected) and the step to try next.
+    fn step<M: Metric>(&self, metric: &M, y: &mut State, dt: f64) -> (f64, f64);
+
+    // Step at r = r_s. Fixed step integrators use base_step * r^2/r_s everywhere.
+    fn base_step(&self) -> f64;
+
+
+    // == Optional ==
+    fn is_adaptive(&self) -> bool {
+        false
+    }
+}
+
+
+// Explicit Euler, updating the velocity before the position
+#[derive(Clone, Copy, Debug)]
+pub struct Euler;
+
+impl Integrator for Euler {
+    fn step<M: Metric>(&self, metric: &M, y: &mut State, dt: f64) -> (f64, f64) {
+        let (mut pos, mut dir) = (pos(y), dir(y));
+        dir += metric.geodesic_acc(&pos, &dir) * dt;
+        pos += dir * dt;
+        *y = state(&pos, &dir);
+        (dt, dt)
+    }
+
+    fn base_step(&self) -> f64 {
+        0.0001
+    }
+}
+
+
+// Classic fourth order Runge-Kutta
+#[derive(Clone, Copy, Debug)]
+pub struct Rk4;
+
+impl Integrator for Rk4 {
+    fn step<M: Metric>(&self, metric: &M, y: &mut State, dt: f64) -> (f64, f64) {
+        let k1 = derivative(metric, y);
+        let k2 = derivative(metric, &(*y + k1 * (dt/2.0)));
+        let k3 = derivative(metric, &(*y + k2 * (dt/2.0)));
+        let k4 = derivative(metric, &(*y + k3 * dt));
+        *y += (k1 + k2 * 2.0 + k3 * 2.0 + k4) * (dt/6.0);
+        (dt, dt)
+    }
+
+    fn base_step(&self) -> f64 {
+        0.01
+    }
+}
+
+
+// Implicit midpoint rule, the Gauss-Legendre Runge-Kutta method of order 2.
+// It is only symplectic in canonical variables, not in (x, dx/dt) and not
+// once the rays are renormalised to be null. The implicit equation is solved
+// by fixed point iteration.
+#[derive(Clone, Copy, Debug)]
+pub struct Midpoint;
+
+impl Integrator for Midpoint {
+    fn step<M: Metric>(&self, metric: &M, y: &mut State, dt: f64) -> (f64, f64) {
+        let mut k = derivative(metric, y);
+        for _ in 0..10 {
+            let new_k = derivative(metric, &(*y + k * (dt/2.0)));
+            let diff = (new_k - k).amax();
+            k = new_k;
+            if diff < 1e-12 * (1.0 + k.amax()) {
+                break;
+            }
+        }
+        *y += k * dt;
+        (dt, dt)
+    }
+
+    fn base_step(&self) -> f64 {
+        0.005
+    }
+}
+
+
+// Embedded Runge-Kutta pair with step size control
+#[derive(Clone, Copy, Debug)]
+pub struct EmbeddedRk {
+    tableau: &'static Tableau,
+    pub tolerance: f64,
+}
+
+#[derive(Debug)]
+struct Tableau {
+    a: [[f64; 6]; 7],
+    b: [f64; 7], // solution
+    e: [f64; 7], // difference with the embedded solution
+    order: i32, // of the embedded solution
+    stages: usize,
+}
+
+// Runge-Kutta-Fehlberg 4(5)
+const RKF45: Tableau = Tableau {
+    a: [
+        [0.0, 0.0, 0.0, 0.0, 0.0, 0.0],
+        [1.0/4.0, 0.0, 0.0, 0.0, 0.0, 0.0],
+        [3.0/32.0, 9.0/32.0, 0.0, 0.0, 0.0, 0.0],
+        [1932.0/2197.0, -7200.0/2197.0, 7296.0/2197.0, 0.0, 0.0, 0.0],
+        [439.0/216.0, -8.0, 3680.0/513.0, -845.0/4104.0, 0.0, 0.0],
+        [-8.0/27.0, 2.0, -3544.0/2565.0, 1859.0/4104.0, -11.0/40.0, 0.0],
+        [0.0, 0.0, 0.0, 0.0, 0.0, 0.0],
+    ],
+    b: [25.0/216.0, 0.0, 1408.0/2565.0, 2197.0/4104.0, -1.0/5.0, 0.0, 0.0],
+    e: [
+        25.0/216.0 - 16.0/135.0,
+        0.0,
+        1408.0/2565.0 - 6656.0/12825.0,
+        2197.0/4104.0 - 28561.0/56430.0,
+        -1.0/5.0 + 9.0/50.0,
+        -2.0/55.0,
+        0.0,
+    ],
+    order: 4,
+    stages: 6,
+};
+
+// Dormand-Prince 5(4), advancing with the fifth order solution
+const DOPRI5: Tableau = Tableau {
+    a: [
+        [0.0, 0.0, 0.0, 0.0, 0.0, 0.0],
+        [1.0/5.0, 0.0, 0.0, 0.0, 0.0, 0.0],
+        [3.0/40.0, 9.0/40.0, 0.0, 0.0, 0.0, 0.0],
+        [44.0/45.0, -56.0/15.0, 32.0/9.0, 0.0, 0.0, 0.0],
+        [19372.0/6561.0, -25360.0/2187.0, 64448.0/6561.0, -212.0/729.0, 0.0, 0.0],
+        [9017.0/3168.0, -355.0/33.0, 46732.0/5247.0, 49.0/176.0, -5103.0/18656.0, 0.0],
+        [35.0/384.0, 0.0, 500.0/1113.0, 125.0/192.0, -2187.0/6784.0, 11.0/84.0],
+    ],
+    b: [35.0/384.0, 0.0, 500.0/1113.0, 125.0/192.0, -2187.0/6784.0, 11.0/84.0, 0.0],
+    e: [
+        35.0/384.0 - 5179.0/57600.0,
+        0.0,
+        500.0/1113.0 - 7571.0/16695.0,
+        125.0/192.0 - 393.0/640.0,
+        -2187.0/6784.0 + 92097.0/339200.0,
+        11.0/84.0 - 187.0/2100.0,
+        -1.0/40.0,
+    ],
+    order: 4,
+    stages: 7,
+};
+
+impl EmbeddedRk {
+    pub fn rkf45(tolerance: f64) -> EmbeddedRk {
+        EmbeddedRk {tableau: &RKF45, tolerance}
+    }
+
+    pub fn dopri5(tolerance: f64) -> EmbeddedRk {
+        EmbeddedRk {tableau: &DOPRI5, tolerance}
+    }
+}
+
+impl Integrator for EmbeddedRk {
+    fn step<M: Metric>(&self, metric: &M, y: &mut State, dt: f64) -> (f64, f64) {
+        let t = self.tableau;
+
+        let mut k = [State::zeros(); 7];
+        for i in 0..t.stages {
+            let mut yi = *y;
+            for (j, kj) in k.iter().enumerate().take(i) {
+                yi += kj * (t.a[i][j] * dt);
+            }
+            k[i] = derivative(metric, &yi);
+        }
+
+        let mut new_y = *y;
+        let mut err = State::zeros();
+        for (i, ki) in k.iter().enumerate() {
+            new_y += ki * (t.b[i] * dt);
+            err += ki * (t.e[i] * dt);
+        }
+
+        // Mixed absolute and relative error, ignoring the time coordinate
+        // which does not change the path of the ray
+        let mut err_norm: f64 = 0.0;
+        for i in 1..8 {
+            let scale = self.tolerance * (1.0 + y[i].abs().max(new_y[i].abs()));
+            err_norm = err_norm.max(err[i].abs() / scale);
+        }
+
+        let factor = if err_norm == 0.0 {
+            5.0
+        } else {
+            (0.9 * err_norm.powf(-1.0 / (t.order + 1) as f64)).clamp(0.2, 5.0)
+        };
+
+        if err_norm <= 1.0 {
+            *y = new_y;
+            (dt, dt * factor)
+        } else {
+            (0.0, dt * factor)
+        }
+    }
+
+    fn base_step(&self) -> f64 {
+        0.01
+    }
+
+    fn is_adaptive(&self) -> bool {
+        true
+    }
+}
+
+
+// Any of the integrators above, chosen at runtime
+#[derive(Clone, Copy, Debug)]
+pub enum AnyIntegrator {
+    Euler(Euler),
+    Rk4(Rk4),
+    Midpoint(Midpoint),
+    Adaptive(EmbeddedRk),
+}
+
+impl AnyIntegrator {
+    // Names as accepted by the CLI
+    pub fn from_name(name: &str, tolerance: f64) -> Option<AnyIntegrator> {
+        match name {
+            "euler" => Some(AnyIntegrator::Euler(Euler)),
+            "rk4" => Some(AnyIntegrator::Rk4(Rk4)),
+            "midpoint" => Some(AnyIntegrator::Midpoint(Midpoint)),
+            "rkf45" => Some(AnyIntegrator::Adaptive(EmbeddedRk::rkf45(tolerance))),
+            "dopri5" => Some(AnyIntegrator::Adaptive(EmbeddedRk::dopri5(tolerance))),
+            _ => None,
+        }
+    }
+}
+
+impl Default for AnyIntegrator {
+    fn default() -> AnyIntegrator {
+        AnyIntegrator::Euler(Euler)
+    }
+}
+
+impl Integrator for AnyIntegrator {
+    fn step<M: Metric>(&self, metric: &M, y: &mut State, dt: f64) -> (f64, f64) {
+        match self {
+            Self::Euler(a) => a.step(metric, y, dt),
+            Self::Rk4(a) => a.step(metric, y, dt),
+            Self::Midpoint(a) => a.step(metric, y, dt),
+            Self::Adaptive(a) => a.step(metric, y, dt),
+        }
+    }
+
+    fn base_step(&self) -> f64 {
+        match self {
+            Self::Euler(a) => a.base_step(),
+            Self::Rk4(a) => a.base_step(),
+            Self::Midpoint(a) => a.base_step(),
+            Self::Adaptive(a) => a.base_step(),
+        }
+    }
+
+    fn is_adaptive(&self) -> bool {
+        match self {
+            Self::Euler(a) => a.is_adaptive(),
+            Self::Rk4(a) => a.is_adaptive(),
+            Self::Midpoint(a) => a.is_adaptive(),
+            Self::Adaptive(a) => a.is_adaptive(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::metric::Schwarzschild;
+
+    // Energy and angular momentum of a ray in the equatorial plane
+    fn conserved(y: &State) -> (f64, f64) {
+        ((1.0 - 1.0/y[1]) * y[4], y[1].powi(2) * y[7])
+    }
+
+    // Relative change of the conserved quantities of a ray deflected by the hole
+    fn conservation_error<I: Integrator>(integrator: &I) -> f64 {
+        let r: f64 = 10.0;
+        let pos = Vector4::new(0.0, r, std::f64::consts::FRAC_PI_2, 0.0);
+        let mut dir = Vector4::<f64>::new(0.0, -0.8, 0.0, 0.06);
+        dir[0] = ((dir[1].powi(2)/(1.0 - 1.0/r) + (r*dir[3]).powi(2))/(1.0 - 1.0/r)).sqrt();
+        let mut y = state(&pos, &dir);
+
+        let (e0, l0) = conserved(&y);
+        let mut dt = integrator.base_step();
+        let mut t = 0.0;
+        while t < 5.0 {
+            if !integrator.is_adaptive() {
+                dt = integrator.base_step() * y[1].powi(2);
+            }
+            let (taken, next) = integrator.step(&Schwarzschild::default(), &mut y, dt.min(5.0 - t).max(1e-9));
+            t += taken;
+            dt = next;
+        }
+        let (e, l) = conserved(&y);
+
+        ((e - e0)/e0).abs().max(((l - l0)/l0).abs())
+    }
+
+    #[test]
+    fn integrators_conserve_energy_and_angular_momentum() {
+        assert!(conservation_error(&Euler) < 1e-2);
+        assert!(conservation_error(&Rk4) < 1e-5);
+        assert!(conservation_error(&Midpoint) < 1e-3);
+        assert!(conservation_error(&EmbeddedRk::rkf45(1e-8)) < 1e-6);
+        assert!(conservation_error(&EmbeddedRk::dopri5(1e-8)) < 1e-6);
+    }
+
+    #[test]
+    fn higher_order_integrators_beat_euler() {
+        let euler = conservation_error(&Euler);
+        assert!(conservation_error(&Rk4) < euler);
+        assert!(conservation_error(&Midpoint) < euler);
+    }
+}
@@ -0,0 +1,289 @@
+use std::f64::consts::PI;
+
+use nalgebra::Vector4;
+
+use rayon::prelude::*;
+
+use crate::metric::Metric;
+use crate::integrator::{self, Integrator};
+
+
+// Spacing of the in-plane angle at which orbits are sampled
+const D_PSI: f64 = 0.005;
+
+// Orbits still going after this many turns are counted as captured, they are
+// within a hair of the photon sphere
+pub const MAX_PSI: f64 = 6.0 * PI;
+
+// How an orbit ends
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum End {
+    Escaped(f64), // in-plane angle of the outgoing direction
+    Captured,
+}
+
+// What a ray followed on its orbital plane runs into first
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Hit {
+    Disk(f64), // radius of the crossing
+    End(End),
+}
+
+// Photon orbit on a plane through the hole, leaving the camera at ψ = 0.
+// Stored as u = 1/r, which unlike r stays smooth as rays fly away.
+#[derive(Clone, Debug)]
+pub struct Orbit {
+    u: Vec<f32>, // at ψ = k * D_PSI
+    end: End,
+}
+
+impl Orbit {
+    // 1/r at the in-plane angle `psi`, if the orbit gets that far
+    fn u(&self, psi: f64) -> Option<f64> {
+        let k = psi / D_PSI;
+        let i = k.floor() as usize;
+        if psi < 0.0 || i + 1 >= self.u.len() {
+            return None;
+        }
+
+        let t = k - i as f64;
+        Some(self.u[i] as f64 * (1.0 - t) + self.u[i + 1] as f64 * t)
+    }
+
+    // Radius at the in-plane angle `psi`, if the orbit gets that far
+    pub fn r(&self, psi: f64) -> Option<f64> {
+        self.u(psi).map(|u| 1.0/u)
+    }
+
+    pub fn end(&self) -> End {
+        self.end
+    }
+}
+
+// Photon orbits of a spherically symmetric metric, leaving a camera at radius
+// `r_cam` at every angle α from the direction to the hole. Every ray from the
+// camera follows one of them on its own plane, so rotating the camera needs
+// no integration at all.
+#[derive(Clone, Debug)]
+pub struct LensingMap {
+    pub r_cam: f64,
+    alphas: Vec<f64>,
+    orbits: Vec<Orbit>,
+}
+
+impl LensingMap {
+    // `samples` orbits, packed around the critical angle between captured and
+    // escaping rays where the deflection changes the fastest
+    pub fn new<M: Metric, I: Integrator>(metric: &M, integrator: &I, r_cam: f64, samples: usize) -> LensingMap {
+        let critical = LensingMap::critical_angle(metric, integrator, r_cam);
+
+        let alphas: Vec<f64> = (0..samples).map(|i| {
+            let s = 2.0 * i as f64 / (samples - 1) as f64 - 1.0;
+            let range = if s < 0.0 { critical } else { PI - critical };
+            (critical + s.powi(3) * range).clamp(0.0, PI)
+        }).collect();
+
+        let orbits = alphas.par_iter().map(|&alpha| trace(metric, integrator, r_cam, alpha)).collect();
+
+        LensingMap {r_cam, alphas, orbits}
+    }
+
+    // Bisects for the angle from the hole below which rays are captured. Rays
+    // closer to it than ~1e-7 wind past MAX_PSI anyway.
+    fn critical_angle<M: Metric, I: Integrator>(metric: &M, integrator: &I, r_cam: f64) -> f64 {
+        let (mut lo, mut hi) = (0.0, PI);
+        for _ in 0..24 {
+            let mid = 0.5 * (lo + hi);
+            match trace(metric, integrator, r_cam, mid).end {
+                End::Captured => lo = mid,
+                End::Escaped(_) => hi = mid,
+            }
+        }
+        0.5 * (lo + hi)
+    }
+
+    // Orbit of the ray leaving at `alpha`, between the two nearest samples
+    pub fn path(&self, alpha: f64) -> Path<'_> {
+        let i = self.alphas.partition_point(|&a| a < alpha).clamp(1, self.alphas.len() - 1);
+        let (a0, a1) = (self.alphas[i - 1], self.alphas[i]);
+        let t = if a1 > a0 { ((alpha - a0) / (a1 - a0)).clamp(0.0, 1.0) } else { 0.0 };
+
+        Path {orbits: [&self.orbits[i - 1], &self.orbits[i]], t}
+    }
+}
+
+// Interpolation between the orbits on each side of a ray
+pub struct Path<'a> {
+    orbits: [&'a Orbit; 2],
+    t: f64,
+}
+
+impl<'a> Path<'a> {
+    fn nearest(&self) -> &'a Orbit {
+        self.orbits[(self.t > 0.5) as usize]
+    }
+
+    // Orbits ending differently straddle the critical angle, mixing them
+    // means nothing
+    fn same_fate(&self) -> bool {
+        matches!(
+            (self.orbits[0].end, self.orbits[1].end),
+            (End::Escaped(_), End::Escaped(_)) | (End::Captured, End::Captured)
+        )
+    }
+
+    pub fn r(&self, psi: f64) -> Option<f64> {
+        match (self.orbits[0].u(psi), self.orbits[1].u(psi)) {
+            (Some(u0), Some(u1)) if self.same_fate() => Some(1.0/(u0 * (1.0 - self.t) + u1 * self.t)),
+            _ => self.nearest().r(psi),
+        }
+    }
+
+    pub fn end(&self) -> End {
+        match (self.orbits[0].end, self.orbits[1].end) {
+            (End::Escaped(b0), End::Escaped(b1)) => End::Escaped(b0 * (1.0 - self.t) + b1 * self.t),
+            _ => self.nearest().end,
+        }
+    }
+
+    // First crossing of the equator accepted by `disk`, every half turn from
+    // `crossing` if any, else where the orbit ends
+    pub fn hit<F: Fn(f64) -> bool>(&self, crossing: Option<f64>, disk: F) -> Hit {
+        if let Some(mut psi) = crossing {
+            while let Some(r) = self.r(psi) {
+                if disk(r) {
+                    return Hit::Disk(r);
+                }
+                psi += PI;
+            }
+        }
+
+        Hit::End(self.end())
+    }
+}
+
+// Integrates the orbit leaving `r_cam` at `alpha` from the direction to the
+// hole, on the equatorial plane where ψ is φ
+fn trace<M: Metric, I: Integrator>(metric: &M, integrator: &I, r_cam: f64, alpha: f64) -> Orbit {
+    let pos = Vector4::new(0.0, r_cam, std::f64::consts::FRAC_PI_2, 0.0);
+    let g = metric.g(&pos);
+    let mut dir = Vector4::new(
+        1.0,
+        -alpha.cos()/g[(1, 1)].sqrt(),
+        0.0,
+        alpha.sin()/g[(3, 3)].sqrt(),
+    );
+    metric.null_norm(&pos, &mut dir);
+
+    let horizon = metric.horizon();
+    let rs = 2.0 * metric.length_scale();
+    let r_escape = (2.0*r_cam).max(30.0*rs);
+
+    // (ψ, u, du/dψ) at every step taken
+    let mut samples = vec![(0.0, 1.0/r_cam, -dir[1]/(dir[3] * r_cam.powi(2)))];
+
+    let mut dt = integrator.base_step() / rs * r_cam.powf(2.0);
+    let mut y = integrator::state(&pos, &dir);
+    let mut end = End::Captured;
+    for _ in 0..1_000_000 {
+        let (pos, dir) = (integrator::pos(&y), integrator::dir(&y));
+
+        if pos.iter().chain(dir.iter()).any(|x| x.is_nan()) || pos[3] > MAX_PSI || pos[1] < 1.01 * horizon {
+            break;
+        }
+
+        if pos[1] > r_escape && dir[1] > 0.0 {
+            end = End::Escaped(pos[3] + (pos[1] * dir[3]).atan2(dir[1]));
+            break;
+        }
+
+        dt = integrator::ray_step(integrator, metric, &pos, &dir, dt);
+        let (taken, next) = integrator.step(metric, &mut y, dt);
+        dt = next;
+
+        if taken > 0.0 {
+            let (new_pos, mut new_dir) = (integrator::pos(&y), integrator::dir(&y));
+            metric.null_norm(&new_pos, &mut new_dir);
+            y = integrator::state(&new_pos, &new_dir);
+
+            samples.push((new_pos[3], 1.0/new_pos[1], -new_dir[1]/(new_dir[3] * new_pos[1].powi(2))));
+        }
+    }
+
+    Orbit {u: resample(&samples), end}
+}
+
+// Cubic Hermite interpolation of the steps onto the uniform ψ grid
+fn resample(samples: &[(f64, f64, f64)]) -> Vec<f32> {
+    let mut u = vec![samples[0].1 as f32];
+    for pair in samples.windows(2) {
+        let ((psi0, u0, du0), (psi1, u1, du1)) = (pair[0], pair[1]);
+        let h = psi1 - psi0;
+        while h > 0.0 && u.len() as f64 * D_PSI <= psi1 {
+            let t = (u.len() as f64 * D_PSI - psi0) / h;
+            let (t2, t3) = (t*t, t*t*t);
+            let value = (2.0*t3 - 3.0*t2 + 1.0)*u0 + (t3 - 2.0*t2 + t)*h*du0
+                + (-2.0*t3 + 3.0*t2)*u1 + (t3 - t2)*h*du1;
+            u.push(value as f32);
+        }
+    }
+    u
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use rand::prelude::*;
+
+    use crate::metric::Schwarzschild;
+    use crate::integrator::Rk4;
+
+    #[test]
+    fn critical_angle_matches_photon_sphere() {
+        let metric = Schwarzschild::new(0.5);
+        let r = 4.0 + 20.0 * random::<f64>();
+
+        // sin α_c = b_c sqrt(1 - r_s/r) / r with b_c = 3 sqrt(3) M
+        let expected = (3.0 * 3f64.sqrt() * 0.5 * (1.0 - 1.0/r).sqrt() / r).asin();
+        let critical = LensingMap::critical_angle(&metric, &Rk4, r);
+        assert!((critical - expected).abs() < 1e-3, "Failed at {}", r);
+    }
+
+    #[test]
+    fn interpolated_paths_match_integration() {
+        let metric = Schwarzschild::new(0.5);
+
+        for &r_cam in [10.0, 20.0].iter() {
+            let map = LensingMap::new(&metric, &Rk4, r_cam, 256);
+            let critical = LensingMap::critical_angle(&metric, &Rk4, r_cam);
+
+            // Away from the critical angle, where neighbouring orbits part
+            let alphas = (0..24).map(|i| (i as f64 + 0.5) * PI / 24.0)
+                .filter(|alpha| (alpha - critical).abs() > 0.05);
+            for alpha in alphas {
+                let path = map.path(alpha);
+                let orbit = trace(&metric, &Rk4, r_cam, alpha);
+
+                match (path.end(), orbit.end()) {
+                    (End::Escaped(beta), End::Escaped(expected)) => {
+                        assert!((beta - expected).abs() < 1e-2, "Failed at {:?}", (r_cam, alpha));
+                    },
+                    // Where captured rays fall in is not compared
+                    (End::Captured, End::Captured) => continue,
+                    ends => panic!("Failed at {:?}", (r_cam, alpha, ends)),
+                }
+
+                // Inside the camera radius, where the disk would be
+                for k in 1..10 {
+                    let psi = 0.3 * k as f64;
+                    if let (Some(r), Some(expected)) = (path.r(psi), orbit.r(psi)) {
+                        if expected < r_cam {
+                            assert!((r - expected).abs() < 1e-2 * expected, "Failed at {:?}", (r_cam, alpha, psi));
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
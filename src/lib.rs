@@ -2,173 +2,184 @@ use std::thread;
 use std::time::{Duration};
 
 
-use image;
 use image::RgbImage;
 
-use sdl2;
-use sdl2::pixels::Color;
-use sdl2::rect::Rect;
-
 use nalgebra::{Unit, Vector3};
 
 
-mod render;
-mod env;
+pub mod render;
+pub mod env;
+pub mod metric;
 mod physics;
+pub mod autodiff;
+pub mod integrator;
+pub mod units;
+pub mod disk;
+pub mod spectral;
+pub mod hdr;
+pub mod tonemap;
+pub mod color;
+pub mod sampling;
+pub mod lensing;
+pub mod binet;
+pub mod analytic;
+
+#[cfg(feature = "window")]
+mod window;
+#[cfg(feature = "window")]
+pub use window::start_windowed;
 
 use render::Renderer;
-use env::{EuclidianRaytracing, SchwarzschildRaytracing, Environment};
-
-pub fn start_windowed(screen: [u32;2], scale: u32, aspect: f64, schwarzschild: bool, skydome: Option<Box<image::RgbImage>>, (r, theta, phi): (f64, f64, f64)) {
-    // SDL2 stuff
-    let sdl = sdl2::init().unwrap();
-    let video = sdl.video().unwrap();
-    let window = video
-        .window("TEST", scale*screen[0], scale*screen[1])
-        .build()
-        .unwrap();
-
-    let mut canvas = window
-        .into_canvas()
-        .build()
-        .unwrap();
-
-    canvas.set_draw_color(Color::RGB(0xff, 0xff, 0xff));
-    canvas.clear();
-    canvas.present();
-
-    // Renderer
-    /*
-    let r = 10.0;
-    let theta = std::f64::consts::FRAC_PI_2 - 0.2;
-    let phi = 0.0; 
-    */
-
-    let mut renderer = render::RayonRenderer::new(screen,
-        if schwarzschild {
-            Env::Schwarz(SchwarzschildRaytracing::new_orbiting_spherical(
-                (r, theta, phi), aspect, skydome.clone()))
-        } else {
-            Env::Euclid(EuclidianRaytracing::new_orbiting_spherical(
-                (r, theta, phi), aspect, skydome.clone()))
-        },
-    );
-    
-    renderer.start_render();
+use env::{EuclidianRaytracing, GeodesicRaytracing, SchwarzschildRaytracing, KerrRaytracing, PainleveRaytracing, ReissnerNordstromRaytracing, KerrNewmanRaytracing, WormholeRaytracing, DeSitterRaytracing, HaywardRaytracing, BardeenRaytracing, Environment, Tracer};
+use metric::{Metric, Schwarzschild, Kerr, PainleveGullstrand, ReissnerNordstrom, KerrNewman, MorrisThorne, SchwarzschildDeSitter, Hayward, Bardeen, Observer};
+use integrator::AnyIntegrator;
+use disk::AnyDisk;
+use hdr::Radiance;
+use tonemap::ToneMapping;
+use sampling::{Sampler, Adaptive};
 
-    // Mouse state
-    let mut last_mouse_pos: Option<(i32, i32)> = None;
-
-    // Main loop
-    let mut event_pump = sdl.event_pump().unwrap();
-    'main: loop {
-        // Events
-        for event in event_pump.poll_iter() {
-            match event {
-                sdl2::event::Event::Quit {..} => break 'main,
-                _ => (),
-            }
+// Spacetime around the camera, with its parameters in units of the mass
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Spacetime {
+    Flat,
+    Schwarzschild,
+    Painleve, // Schwarzschild in Painlevé-Gullstrand coordinates
+    Kerr {spin: f64},
+    ReissnerNordstrom {charge: f64},
+    KerrNewman {spin: f64, charge: f64},
+    DeSitter {lambda: f64}, // ΛM^2
+    Wormhole {throat: f64}, // in scene units, it has no mass
+    Hayward {length: f64},
+    Bardeen {length: f64},
+}
+
+impl Spacetime {
+    // Names as accepted by the CLI. The parameters are looked up by the name
+    // of their CLI option, None if one is missing.
+    pub fn from_name<F: Fn(&str) -> Option<f64>>(name: &str, param: F) -> Option<Spacetime> {
+        match name {
+            "flat" => Some(Spacetime::Flat),
+            "schwarzschild" => Some(Spacetime::Schwarzschild),
+            "painleve" => Some(Spacetime::Painleve),
+            "kerr" => Some(Spacetime::Kerr {spin: param("spin")?}),
+            "reissner-nordstrom" => Some(Spacetime::ReissnerNordstrom {charge: param("charge")?}),
+            "kerr-newman" => Some(Spacetime::KerrNewman {spin: param("spin")?, charge: param("charge")?}),
+            "de-sitter" => Some(Spacetime::DeSitter {lambda: param("lambda")?}),
+            "wormhole" => Some(Spacetime::Wormhole {throat: param("wormhole")?}),
+            "hayward" => Some(Spacetime::Hayward {length: param("length")?}),
+            "bardeen" => Some(Spacetime::Bardeen {length: param("length")?}),
+            _ => None,
         }
-        
-        
-        canvas.set_draw_color(Color::RGB(0x00, 0x00, 0x10));
-        canvas.clear();
-        
-        // Rendering
-        let pixels = renderer.get_pixels();
-        for ii in 0..pixels.len() {
-            let ii = ii as u32;
-            let i = (ii / screen[0]) * scale;
-            let j = (ii % screen[0]) * scale;
-            
-            canvas.set_draw_color(pixels[ii as usize]);
-            canvas.fill_rect(Rect::new(j as i32, i as i32, scale, scale)).unwrap();
+    }
+
+    // a/M, which sets the inner edge of the disk
+    pub fn spin(&self) -> f64 {
+        match self {
+            Self::Kerr {spin} | Self::KerrNewman {spin, ..} => *spin,
+            _ => 0.0,
         }
-   
-        // Mouse orbiting
-        if event_pump.mouse_state().left() {
-            match last_mouse_pos {
-                Some(last_pos) => {
-                    let pos = (event_pump.mouse_state().x(), event_pump.mouse_state().y());
-
-                    let (dx, dy) =  (pos.0 - last_pos.0, pos.1 - last_pos.1);
-
-                    last_mouse_pos = Some(pos);
-
-                    if (dx, dy) != (0, 0) {
-                        let pos = renderer.env.pos();
-
-                        let mut pos = (
-                            pos.norm(),
-                            (pos.x.powf(2.0) + pos.y.powf(2.0)).sqrt().atan2(pos.z),
-                            pos.y.atan2(pos.x),
-                        );
-                        
-                        if pos.1 < 0.0 {
-                            pos.1 += std::f64::consts::TAU;
-                        }
-                        
-                        if pos.2 < 0.0 {
-                            pos.2 += std::f64::consts::TAU;
-                        }
-
-                        let new_pos = Vector3::new(
-                            pos.0,
-                            pos.1 - (dy as f64)/(std::f64::consts::PI*100.0),
-                            pos.2 - (dx as f64)/(std::f64::consts::TAU*100.0),
-                        );
-                        
-                        renderer.env.set_pos_orbiting(&new_pos);
-                        renderer.start_render();
-                    }
-                },
-                None => {
-                    last_mouse_pos = Some((event_pump.mouse_state().x(), event_pump.mouse_state().y()));
-                },
-            }
-        } else {
-            last_mouse_pos = None;
+    }
+
+    // Q/M, which also moves the inner edge of the disk
+    pub fn charge(&self) -> f64 {
+        match self {
+            Self::ReissnerNordstrom {charge} | Self::KerrNewman {charge, ..} => *charge,
+            _ => 0.0,
         }
+    }
+}
+
+// What to render and from where
+#[derive(Clone)]
+pub struct Scene {
+    pub spacetime: Spacetime,
+    pub mass: f64, // geometric, r_s = 2 * mass
+    pub integrator: AnyIntegrator,
+    pub tracer: Tracer, // Schwarzschild only
+    pub disk: AnyDisk,
+    pub skydome: Option<Box<image::RgbImage>>,
+    pub far_skydome: Option<Box<image::RgbImage>>, // through the wormhole
+    pub camera: (f64, f64, f64), // r, theta, phi
+    pub observer: Observer, // how the camera moves, ignored without a hole
+    pub aspect: f64,
+    pub tone_mapping: ToneMapping,
+    pub sampler: Sampler, // only used by render_image
+    pub adaptive: Option<Adaptive>, // replaces `sampler` if set
+}
+
+// Saves the raw radiance if `path` is a .hdr or .exr, else the tone mapped image
+pub fn render_image(screen: [u32;2], scene: Scene, path: &str) {
+    let tone_mapping = scene.tone_mapping;
+    let pixels = render_pixels(screen, scene);
+
+    save_pixels(screen, &pixels, &tone_mapping, path);
+}
 
-        canvas.present();
-        thread::sleep(Duration::new(0, (1e9 as u32) / 60));
+// Renders `scene` as a Kerr-Newman hole for every spin across and every
+// charge down, each cell `screen` large, and saves the grid like
+// render_image. Cells past extremality, without a horizon, are left black.
+pub fn render_sheet(screen: [u32;2], scene: Scene, spins: &[f64], charges: &[f64], path: &str) {
+    let sheet = [screen[0] * spins.len() as u32, screen[1] * charges.len() as u32];
+    let mut pixels = vec![Radiance::zeros(); (sheet[0] * sheet[1]) as usize];
+
+    for (row, &charge) in charges.iter().enumerate() {
+        for (col, &spin) in spins.iter().enumerate() {
+            if !KerrNewman::new(scene.mass, spin, charge).is_hole() {
+                continue;
+            }
+
+            let mut disk = scene.disk;
+            disk.set_hole(spin, charge);
+
+            let cell = render_pixels(screen, Scene {spacetime: Spacetime::KerrNewman {spin, charge}, disk, ..scene.clone()});
+            for (ii, radiance) in cell.into_iter().enumerate() {
+                let ii = ii as u32;
+                let i = row as u32 * screen[1] + ii / screen[0];
+                let j = col as u32 * screen[0] + ii % screen[0];
+
+                pixels[(i * sheet[0] + j) as usize] = radiance;
+            }
+        }
     }
+
+    save_pixels(sheet, &pixels, &scene.tone_mapping, path);
 }
 
-pub fn render_image(screen: [u32;2], aspect: f64, schwarzschild: bool, skydome: Option<Box<image::RgbImage>>, (r, theta, phi): (f64, f64, f64), path: &str) {
-    let mut renderer = render::RayonRenderer::new(screen,
-        if schwarzschild {
-            Env::Schwarz(SchwarzschildRaytracing::new_orbiting_spherical(
-                (r, theta, phi), aspect, skydome.clone()))
-        } else {
-            Env::Euclid(EuclidianRaytracing::new_orbiting_spherical(
-                (r, theta, phi), aspect, skydome.clone()))
-        },
-    );
+fn render_pixels(screen: [u32;2], scene: Scene) -> Vec<Radiance> {
+    let (sampler, adaptive) = (scene.sampler, scene.adaptive);
+    let mut renderer = match adaptive {
+        Some(adaptive) => render::RayonRenderer::new_adaptive(screen, Env::new(scene), adaptive),
+        None => render::RayonRenderer::new_sampled(screen, Env::new(scene), sampler),
+    };
     
     renderer.start_render();
 
-    // Creates an image
-    let mut img = RgbImage::new(screen[0], screen[1]);
-    
     // Rendering
     while !renderer.is_ready() {
         thread::sleep(Duration::from_millis(10));
     }
 
-    let pixels = renderer.get_pixels();
+    renderer.get_pixels()
+}
+
+fn save_pixels(screen: [u32;2], pixels: &[Radiance], tone_mapping: &ToneMapping, path: &str) {
+    if hdr::is_hdr_path(path) {
+        hdr::save(path, screen[0], screen[1], pixels).unwrap();
+        println!("Written image");
+        return;
+    }
+
+    // Creates an image
+    let mut img = RgbImage::new(screen[0], screen[1]);
 
     for ii in 0..pixels.len() {
         let ii = ii as u32;
         let i = ii / screen[0];
         let j = ii % screen[0];
         
-        //canvas.set_draw_color(pixels[ii as usize]);
-        let pixel = pixels[ii as usize];
-        let pixel: image::Rgb<u8> = [pixel.r, pixel.g, pixel.b].into();
+        let pixel: image::Rgb<u8> = tone_mapping.apply(&pixels[ii as usize]).into();
 
         img[(j, i)] = pixel;
-        //canvas.fill_rect(Rect::new(j as i32, i as i32, scale, scale)).unwrap();
     }
 
     img.save(path).unwrap();
@@ -179,13 +190,70 @@ pub fn render_image(screen: [u32;2], aspect: f64, schwarzschild: bool, skydome:
 enum Env {
     Euclid(EuclidianRaytracing),
     Schwarz(SchwarzschildRaytracing),
+    Kerr(KerrRaytracing),
+    Painleve(PainleveRaytracing),
+    ReissnerNordstrom(ReissnerNordstromRaytracing),
+    KerrNewman(KerrNewmanRaytracing),
+    Wormhole(WormholeRaytracing),
+    DeSitter(DeSitterRaytracing),
+    Hayward(HaywardRaytracing),
+    Bardeen(BardeenRaytracing),
+}
+
+impl Env {
+    fn new(scene: Scene) -> Env {
+        let (mass, tracer) = (scene.mass, scene.tracer);
+
+        match scene.spacetime {
+            Spacetime::Flat => {
+                let Scene {camera, aspect, disk, skydome, ..} = scene;
+                Env::Euclid(EuclidianRaytracing::new_orbiting_spherical(camera, aspect, mass, disk, skydome))
+            },
+            Spacetime::Schwarzschild => {
+                let mut env = orbiting(Schwarzschild::new(mass), scene);
+                env.set_tracer(tracer);
+                Env::Schwarz(env)
+            },
+            Spacetime::Painleve => Env::Painleve(orbiting(PainleveGullstrand::new(mass), scene)),
+            Spacetime::Kerr {spin} => Env::Kerr(orbiting(Kerr::new(mass, spin), scene)),
+            Spacetime::ReissnerNordstrom {charge} => Env::ReissnerNordstrom(orbiting(ReissnerNordstrom::new(mass, charge), scene)),
+            Spacetime::KerrNewman {spin, charge} => Env::KerrNewman(orbiting(KerrNewman::new(mass, spin, charge), scene)),
+            Spacetime::DeSitter {lambda} => {
+                let mut sds = SchwarzschildDeSitter::new(mass, lambda);
+                sds.set_camera_radius(scene.camera.0);
+                Env::DeSitter(orbiting(sds, scene))
+            },
+            // Massless, nothing would orbit in a disk
+            Spacetime::Wormhole {throat} => Env::Wormhole(orbiting(MorrisThorne::new(throat), Scene {disk: AnyDisk::None, ..scene})),
+            Spacetime::Hayward {length} => Env::Hayward(orbiting(Hayward::new(mass, length), scene)),
+            Spacetime::Bardeen {length} => Env::Bardeen(orbiting(Bardeen::new(mass, length), scene)),
+        }
+    }
+}
+
+// Geodesic raytracing of `metric` orbiting the camera of `scene`
+fn orbiting<M: Metric>(metric: M, scene: Scene) -> GeodesicRaytracing<M> {
+    let Scene {integrator, disk, skydome, far_skydome, camera, observer, aspect, ..} = scene;
+
+    let mut env = GeodesicRaytracing::new_orbiting_spherical(metric, integrator, disk, camera, aspect, skydome);
+    env.set_far_skydome(far_skydome);
+    env.set_observer(observer);
+    env
 }
 
 impl Environment for Env {
-    fn raytrace(&self, coords: (f64, f64)) -> Color {
+    fn raytrace(&self, coords: (f64, f64)) -> Radiance {
         match self {
             Self::Euclid(euclid) => euclid.raytrace(coords),
             Self::Schwarz(schwarz) => schwarz.raytrace(coords),
+            Self::Kerr(kerr) => kerr.raytrace(coords),
+            Self::Painleve(painleve) => painleve.raytrace(coords),
+            Self::ReissnerNordstrom(rn) => rn.raytrace(coords),
+            Self::KerrNewman(kn) => kn.raytrace(coords),
+            Self::Wormhole(wormhole) => wormhole.raytrace(coords),
+            Self::DeSitter(sds) => sds.raytrace(coords),
+            Self::Hayward(hayward) => hayward.raytrace(coords),
+            Self::Bardeen(bardeen) => bardeen.raytrace(coords),
         }
     }
     
@@ -193,13 +261,29 @@ impl Environment for Env {
         match self {
             Self::Euclid(a) => a.get_data(),
             Self::Schwarz(a) => a.get_data(),
+            Self::Kerr(a) => a.get_data(),
+            Self::Painleve(a) => a.get_data(),
+            Self::ReissnerNordstrom(a) => a.get_data(),
+            Self::KerrNewman(a) => a.get_data(),
+            Self::Wormhole(a) => a.get_data(),
+            Self::DeSitter(a) => a.get_data(),
+            Self::Hayward(a) => a.get_data(),
+            Self::Bardeen(a) => a.get_data(),
         }
     }
 
     fn set_data(&mut self, pos: &Vector3<f64>, dir: &Vector3<f64>, up: &Vector3<f64>) {
         match self{
-            Self::Euclid(a) => a.set_data(&pos, &dir, &up),
-            Self::Schwarz(a) => a.set_data(&pos, &dir, &up),
+            Self::Euclid(a) => a.set_data(pos, dir, up),
+            Self::Schwarz(a) => a.set_data(pos, dir, up),
+            Self::Kerr(a) => a.set_data(pos, dir, up),
+            Self::Painleve(a) => a.set_data(pos, dir, up),
+            Self::ReissnerNordstrom(a) => a.set_data(pos, dir, up),
+            Self::KerrNewman(a) => a.set_data(pos, dir, up),
+            Self::Wormhole(a) => a.set_data(pos, dir, up),
+            Self::DeSitter(a) => a.set_data(pos, dir, up),
+            Self::Hayward(a) => a.set_data(pos, dir, up),
+            Self::Bardeen(a) => a.set_data(pos, dir, up),
         }
     }
 }
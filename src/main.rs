@@ -1,6 +1,32 @@
+use std::cell::RefCell;
+
 use clap::{App, load_yaml};
 
-use rust_blackhole::{start_windowed, render_image};
+use rust_blackhole::{render_image, render_sheet, Scene, Spacetime};
+#[cfg(feature = "window")]
+use rust_blackhole::start_windowed;
+use rust_blackhole::integrator::AnyIntegrator;
+use rust_blackhole::env::Tracer;
+use rust_blackhole::metric::{Observer, KerrNewman, Hayward, Bardeen};
+use rust_blackhole::disk::AnyDisk;
+use rust_blackhole::tonemap::{ToneMapping, Operator};
+use rust_blackhole::sampling::{Sampler, Adaptive, Filter};
+
+// Spacetime picked when none is named, the first one whose options are all
+// present, else flat
+const IMPLIED: &[(&str, &[&str])] = &[
+    ("wormhole", &["wormhole"]),
+    ("kerr-newman", &["spin", "charge"]),
+    ("kerr", &["spin"]),
+    ("reissner-nordstrom", &["charge"]),
+    ("de-sitter", &["lambda"]),
+    ("hayward", &["length"]),
+    ("painleve", &["painleve"]),
+    ("schwarzschild", &["schwarzschild"]),
+];
+
+// Options holding the parameters of some spacetime
+const PARAMETERS: &[&str] = &["spin", "charge", "lambda", "wormhole", "length"];
 
 fn main() {
     // == Deal with CLI arguments ==
@@ -8,6 +34,7 @@ fn main() {
     let matches = App::from_yaml(args_file).get_matches();
 
     // Screen
+    #[cfg(feature = "window")]
     let scale: u32 = matches.value_of("scale").unwrap_or("1").parse().unwrap();
     
     let screen_raw = matches.value_of("screen").unwrap_or("400");
@@ -24,9 +51,88 @@ fn main() {
     let aspect = screen[0] as f64 / screen[1] as f64;
 
     // Parameters
-    let schwarzschild: bool = matches.is_present("schwarzschild");
+    let metric = matches.value_of("metric").unwrap_or_else(|| {
+        IMPLIED.iter()
+            .find(|(_, options)| options.iter().all(|&option| matches.is_present(option)))
+            .map_or("flat", |(name, _)| name)
+    });
+    // Options read by the spacetime, the last one is missing if it fails
+    let read = RefCell::new(Vec::new());
+    let spacetime = Spacetime::from_name(metric, |option| {
+        read.borrow_mut().push(option.to_string());
+        matches.value_of(option).map(|value| value.parse().unwrap())
+    }).unwrap_or_else(|| {
+        eprintln!("--metric {} requires --{}", metric, read.borrow().last().unwrap());
+        std::process::exit(1);
+    });
+    if let Some(option) = PARAMETERS.iter().find(|&&option| {
+        matches.is_present(option) && !read.borrow().iter().any(|read| read == option)
+    }) {
+        eprintln!("--{} is not a parameter of the {} spacetime", option, metric);
+        std::process::exit(1);
+    }
+    let mass: f64 = matches.value_of("mass").unwrap_or("0.5").parse().unwrap();
+    if !KerrNewman::new(mass, spacetime.spin(), spacetime.charge()).is_hole() {
+        eprintln!("No horizon with spin^2 + charge^2 > 1, the singularity would be naked");
+        std::process::exit(1);
+    }
+    let regular_hole = match spacetime {
+        Spacetime::Hayward {length} => Hayward::new(mass, length).is_hole(),
+        Spacetime::Bardeen {length} => Bardeen::new(mass, length).is_hole(),
+        _ => true,
+    };
+    if !regular_hole {
+        eprintln!("No horizon with --length past 4/(3 sqrt 3) ~ 0.77, the core would be visible");
+        std::process::exit(1);
+    }
+    if let Spacetime::DeSitter {lambda} = spacetime {
+        if !(lambda > 0.0 && lambda < 1.0/9.0) {
+            eprintln!("--lambda must be between 0 and 1/9 for both horizons, not {}", lambda);
+            std::process::exit(1);
+        }
+    }
+
+    let tolerance: f64 = matches.value_of("tolerance").unwrap_or("1e-6").parse().unwrap();
+    let integrator = match matches.value_of("integrator") {
+        Some(name) => AnyIntegrator::from_name(name, tolerance).unwrap(),
+        None => AnyIntegrator::default(),
+    };
+    let tracer = Tracer::from_name(matches.value_of("tracer").unwrap_or("geodesic")).unwrap();
+    // The sheet is all Kerr-Newman
+    if tracer != Tracer::Geodesic && (spacetime != Spacetime::Schwarzschild || matches.is_present("sheet")) {
+        eprintln!("--tracer {} only follows rays around a schwarzschild hole", matches.value_of("tracer").unwrap());
+        std::process::exit(1);
+    }
+
+    let solar_masses: f64 = matches.value_of("solar-masses").unwrap_or("6.5e9").parse().unwrap();
+    let accretion_rate: f64 = matches.value_of("accretion-rate").unwrap_or("0.01").parse().unwrap();
+    let disk = AnyDisk::from_name(
+        matches.value_of("disk").unwrap_or("simple"),
+        mass,
+        spacetime.spin(),
+        spacetime.charge(),
+        solar_masses,
+        accretion_rate,
+    ).unwrap();
+
+    // Output
+    let tone_mapping = ToneMapping::new(
+        matches.value_of("exposure").unwrap_or("1.0").parse().unwrap(),
+        Operator::from_name(matches.value_of("tonemap").unwrap_or("clamp")).unwrap(),
+        matches.value_of("gamma").map(|gamma| gamma.parse().unwrap()),
+    );
+
+    let filter = Filter::from_name(matches.value_of("filter").unwrap_or("box")).unwrap();
+    let sampler = Sampler::new(matches.value_of("spp").unwrap_or("1").parse().unwrap(), filter);
+    let adaptive = matches.value_of("budget").map(|budget| {
+        Adaptive::new(
+            budget.parse().unwrap(),
+            matches.value_of("max-spp").unwrap_or("64").parse().unwrap(),
+            filter,
+        )
+    });
     
-    let skydome = match matches.value_of("skydome") {
+    let load_skydome = |arg| match matches.value_of(arg) {
         Some(path) => {
             match image::open(path) {
                 Ok(image) => {
@@ -37,13 +143,53 @@ fn main() {
         },
         None => None,
     };
+    let skydome = load_skydome("skydome");
+    let far_skydome = load_skydome("far-skydome");
 
     let r: f64 = matches.value_of("cam-r").unwrap_or("10.0").parse().unwrap();
     let theta: f64 = matches.value_of("cam-theta").unwrap_or("asdf").parse().unwrap_or(std::f64::consts::FRAC_PI_2 - 0.2);
     let phi: f64 = matches.value_of("cam-phi").unwrap_or("0.0").parse().unwrap();
+    let observer = Observer::from_name(matches.value_of("observer").unwrap_or("static")).unwrap();
+
+    let scene = Scene {
+        spacetime,
+        mass,
+        integrator,
+        tracer,
+        disk,
+        skydome,
+        far_skydome,
+        camera: (r, theta, phi),
+        observer,
+        aspect,
+        tone_mapping,
+        sampler,
+        adaptive,
+    };
 
-    match matches.value_of("image") {
-        Some(path) => render_image(screen, aspect, schwarzschild, skydome, (r, theta, phi), path),
-        None => start_windowed(screen, scale, aspect, schwarzschild, skydome, (r, theta, phi)),
+    // Contact sheet of Kerr-Newman holes, a/M and Q/M evenly from 0 to 1
+    let sheet = matches.value_of("sheet").map(|sheet_raw| {
+        let steps = |n: usize| (0..n).map(|i| i as f64 / (n.max(2) - 1) as f64).collect::<Vec<f64>>();
+        let sheet: Vec<usize> = match sheet_raw.parse::<usize>() {
+            Ok(sheet) => vec![sheet, sheet],
+            Err(_) => sheet_raw.split("x").map(|x| x.parse().ok()).collect::<Option<_>>().unwrap_or_default(),
+        };
+        if sheet.len() != 2 || sheet.contains(&0) {
+            eprintln!("--sheet takes COLSxROWS or a single count for both, not {}", sheet_raw);
+            std::process::exit(1);
+        }
+        (steps(sheet[0]), steps(sheet[1]))
+    });
+
+    match (matches.value_of("image"), sheet) {
+        (Some(path), Some((spins, charges))) => render_sheet(screen, scene, &spins, &charges, path),
+        (Some(path), None) => render_image(screen, scene, path),
+        #[cfg(feature = "window")]
+        (None, _) => start_windowed(screen, scale, scene),
+        #[cfg(not(feature = "window"))]
+        (None, _) => {
+            eprintln!("Built without the window feature, use --image to render to a file");
+            std::process::exit(1);
+        },
     };
 }
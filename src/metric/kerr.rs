@@ -0,0 +1,160 @@
+use nalgebra::{Matrix4, Vector4};
+
+use super::*;
+
+// Kerr metric in Boyer-Lindquist coordinates (t, r, theta, phi), of mass
+// `mass` and with `a` the angular momentum per unit mass.
+#[derive(Clone, Copy, Debug)]
+pub struct Kerr {
+    pub mass: f64,
+    pub a: f64,
+}
+
+impl Kerr {
+    // `spin` is the dimensionless a/M
+    pub fn new(mass: f64, spin: f64) -> Kerr {
+        Kerr {mass, a: spin * mass}
+    }
+}
+
+impl Metric for Kerr {
+    fn g(&self, pos: &Vector4<f64>) -> Matrix4<f64> {
+        let (m, a) = (self.mass, self.a);
+        let (r, theta) = (pos[1], pos[2]);
+        let (s, c) = theta.sin_cos();
+
+        let sigma = r.powi(2) + (a*c).powi(2);
+        let delta = r.powi(2) - 2.0*m*r + a.powi(2);
+
+        let g_tt = -(1.0 - 2.0*m*r/sigma);
+        let g_tphi = -2.0*m*a*r*s.powi(2)/sigma;
+        let g_rr = sigma/delta;
+        let g_thth = sigma;
+        let g_phiphi = (r.powi(2) + a.powi(2) + 2.0*m*a.powi(2)*r*s.powi(2)/sigma)*s.powi(2);
+
+        Matrix4::new(
+            g_tt,   0.0,  0.0,    g_tphi,
+            0.0,    g_rr, 0.0,    0.0,
+            0.0,    0.0,  g_thth, 0.0,
+            g_tphi, 0.0,  0.0,    g_phiphi,
+        )
+    }
+
+    fn g_inv(&self, pos: &Vector4<f64>) -> Matrix4<f64> {
+        let (m, a) = (self.mass, self.a);
+        let (r, theta) = (pos[1], pos[2]);
+        let (s, c) = theta.sin_cos();
+
+        let sigma = r.powi(2) + (a*c).powi(2);
+        let delta = r.powi(2) - 2.0*m*r + a.powi(2);
+        let big_a = (r.powi(2) + a.powi(2)).powi(2) - (a*s).powi(2)*delta;
+
+        let g_tt = -big_a/(sigma*delta);
+        let g_tphi = -2.0*m*a*r/(sigma*delta);
+        let g_rr = delta/sigma;
+        let g_thth = 1.0/sigma;
+        let g_phiphi = (delta - (a*s).powi(2))/(sigma*delta*s.powi(2));
+
+        Matrix4::new(
+            g_tt,   0.0,  0.0,    g_tphi,
+            0.0,    g_rr, 0.0,    0.0,
+            0.0,    0.0,  g_thth, 0.0,
+            g_tphi, 0.0,  0.0,    g_phiphi,
+        )
+    }
+
+    // Only r and theta derivatives are non zero
+    fn dg(&self, pos: &Vector4<f64>) -> [Matrix4<f64>; 4] {
+        let (m, a) = (self.mass, self.a);
+        let (r, theta) = (pos[1], pos[2]);
+        let (s, c) = theta.sin_cos();
+
+        let sigma = r.powi(2) + (a*c).powi(2);
+        let delta = r.powi(2) - 2.0*m*r + a.powi(2);
+        let dr_sigma = 2.0*r;
+        let dth_sigma = -2.0*a.powi(2)*s*c;
+        let dr_delta = 2.0*r - 2.0*m;
+
+        let mut dr = Matrix4::zeros();
+        dr[(0, 0)] = 2.0*m*(sigma - r*dr_sigma)/sigma.powi(2);
+        dr[(0, 3)] = -2.0*m*a*s.powi(2)*(sigma - r*dr_sigma)/sigma.powi(2);
+        dr[(3, 0)] = dr[(0, 3)];
+        dr[(1, 1)] = (dr_sigma*delta - sigma*dr_delta)/delta.powi(2);
+        dr[(2, 2)] = dr_sigma;
+        dr[(3, 3)] = 2.0*r*s.powi(2)
+            + 2.0*m*a.powi(2)*s.powi(4)*(sigma - r*dr_sigma)/sigma.powi(2);
+
+        let mut dth = Matrix4::zeros();
+        dth[(0, 0)] = -2.0*m*r*dth_sigma/sigma.powi(2);
+        dth[(0, 3)] = -2.0*m*a*r*(2.0*s*c*sigma - s.powi(2)*dth_sigma)/sigma.powi(2);
+        dth[(3, 0)] = dth[(0, 3)];
+        dth[(1, 1)] = dth_sigma/delta;
+        dth[(2, 2)] = dth_sigma;
+        dth[(3, 3)] = 2.0*(r.powi(2) + a.powi(2))*s*c
+            + 2.0*m*a.powi(2)*r*(4.0*s.powi(3)*c*sigma - s.powi(4)*dth_sigma)/sigma.powi(2);
+
+        [Matrix4::zeros(), dr, dth, Matrix4::zeros()]
+    }
+
+    fn gamma(&self, pos: &Vector4<f64>) -> Christoffel {
+        let g_inv = self.g_inv(pos);
+        let dg = self.dg(pos);
+
+        christoffel(&g_inv, &dg)
+    }
+
+    // Outer horizon
+    fn horizon(&self) -> f64 {
+        let (m, a) = (self.mass, self.a);
+        m + (m.powi(2) - a.powi(2)).max(0.0).sqrt()
+    }
+
+    fn mass(&self) -> f64 {
+        self.mass
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use rand::prelude::*;
+    use nalgebra as na;
+
+    #[test]
+    fn g_inv_is_inverse() {
+        let pos = na::Vector4::<f64>::new(random(), random(), random(), random());
+        let pos = 100.0 * pos + na::Vector4::new(0.0, 1.01, 0.1, 0.0);
+        let kerr = Kerr::new(0.5, random());
+
+        let id = kerr.g(&pos) * kerr.g_inv(&pos);
+        for mu in 0..4 {
+            for nu in 0..4 {
+                let expected = if mu == nu { 1.0 } else { 0.0 };
+                assert!((id[(mu, nu)] - expected).abs() < 1e-6, "Failed at {:?}", (mu, nu));
+            }
+        }
+    }
+
+    #[test]
+    fn gamma_without_spin_is_schwarzschild() {
+        let pos = na::Vector4::<f64>::new(random(), random(), random(), random());
+        let pos = 100.0 * pos + na::Vector4::new(0.0, 1.01, 0.1, 0.0);
+
+        assert_christoffel_matches(&Kerr::new(0.5, 0.0), &Schwarzschild::new(0.5), &pos);
+    }
+
+    #[test]
+    fn keplerian_omega_is_exact() {
+        let r = 3.0 + 100.0 * random::<f64>();
+        let kerr = Kerr::new(0.5, random());
+        let (m, a) = (kerr.mass, kerr.a);
+
+        let expected = m.sqrt()/(r.powf(1.5) + a*m.sqrt());
+        assert!((kerr.keplerian_omega(r) - expected).abs() < 1e-9 * expected);
+
+        // Through the derivatives recovered from the Christoffel symbols
+        let expected = (m/r.powi(3)).sqrt();
+        assert!((crate::metric::Schwarzschild::new(m).keplerian_omega(r) - expected).abs() < 1e-9 * expected);
+    }
+}
@@ -0,0 +1,89 @@
+use crate::autodiff::Scalar;
+
+use super::*;
+
+// Kerr-Newman metric of a charged, rotating hole in Boyer-Lindquist
+// coordinates, of mass `mass`, angular momentum per unit mass `a` and charge
+// `q`. It is Kerr with 2Mr replaced by 2Mr - Q^2.
+#[derive(Clone, Copy, Debug)]
+pub struct KerrNewman {
+    pub mass: f64,
+    pub a: f64,
+    pub q: f64,
+}
+
+impl KerrNewman {
+    // `spin` and `charge` are the dimensionless a/M and Q/M, with
+    // spin^2 + charge^2 <= 1 for a hole
+    pub fn new(mass: f64, spin: f64, charge: f64) -> KerrNewman {
+        KerrNewman {mass, a: spin * mass, q: charge * mass}
+    }
+
+    // Whether there is a horizon hiding the singularity
+    pub fn is_hole(&self) -> bool {
+        self.a.powi(2) + self.q.powi(2) <= self.mass.powi(2)
+    }
+}
+
+impl MetricComponents for KerrNewman {
+    fn components<T: Scalar>(&self, pos: &[T; 4]) -> [[T; 4]; 4] {
+        let zero = T::from(0.0);
+        let (m, a, q) = (self.mass, self.a, self.q);
+        let (r, theta) = (pos[1], pos[2]);
+        let (s, c) = (theta.sin(), theta.cos());
+
+        let sigma = r.powi(2) + c.powi(2) * a.powi(2);
+        let delta = r.powi(2) - r * (2.0*m) + a.powi(2) + q.powi(2);
+        // (2Mr - Q^2)/Σ, 2M/r in Schwarzschild
+        let w = (r * (2.0*m) - q.powi(2)) / sigma;
+
+        let g_tt = w - 1.0;
+        let g_tphi = -(w * s.powi(2) * a);
+        let g_phiphi = (r.powi(2) + a.powi(2) + w * s.powi(2) * a.powi(2)) * s.powi(2);
+
+        [
+            [g_tt,   zero,          zero,  g_tphi],
+            [zero,   sigma / delta, zero,  zero],
+            [zero,   zero,          sigma, zero],
+            [g_tphi, zero,          zero,  g_phiphi],
+        ]
+    }
+
+    // Outer horizon
+    fn horizon(&self) -> f64 {
+        let (m, a, q) = (self.mass, self.a, self.q);
+        m + (m.powi(2) - a.powi(2) - q.powi(2)).max(0.0).sqrt()
+    }
+
+    fn mass(&self) -> f64 {
+        self.mass
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use rand::prelude::*;
+
+    #[test]
+    fn reduces_to_kerr_and_reissner_nordstrom() {
+        let pos = Vector4::<f64>::new(random(), random(), random(), random());
+        let pos = 100.0 * pos + Vector4::new(0.0, 1.01, 0.1, 0.0);
+        let (spin, charge) = (random::<f64>(), random::<f64>());
+
+        assert_christoffel_matches(&KerrNewman::new(0.5, spin, 0.0), &Kerr::new(0.5, spin), &pos);
+        assert_christoffel_matches(&KerrNewman::new(0.5, 0.0, charge), &ReissnerNordstrom::new(0.5, charge), &pos);
+    }
+
+    #[test]
+    fn horizon_is_where_delta_vanishes() {
+        let (spin, charge) = (random::<f64>(), random::<f64>());
+        let kn = KerrNewman::new(0.5, spin.min(1.0 - charge), charge);
+        assert!(kn.is_hole());
+
+        // g_rr = Σ/Δ
+        let pos = Vector4::new(0.0, Metric::horizon(&kn), 0.1 + 3.0 * random::<f64>(), 0.0);
+        assert!(kn.g(&pos)[(1, 1)].recip().abs() < 1e-9, "Failed at {:?}", (kn.a, kn.q));
+    }
+}
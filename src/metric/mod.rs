@@ -0,0 +1,398 @@
+use nalgebra::{Matrix4, Vector4};
+
+use crate::physics;
+use crate::autodiff::{Dual, Scalar};
+
+
+mod schwarzschild;
+pub use schwarzschild::*;
+
+mod kerr;
+pub use kerr::*;
+
+mod painleve;
+pub use painleve::*;
+
+mod reissner_nordstrom;
+pub use reissner_nordstrom::*;
+
+mod kerr_newman;
+pub use kerr_newman::*;
+
+mod wormhole;
+pub use wormhole::*;
+
+mod schwarzschild_de_sitter;
+pub use schwarzschild_de_sitter::*;
+
+mod regular;
+pub use regular::*;
+
+
+// Christoffel symbols, gamma[lambda][(mu, nu)] = Γ^λ_μν
+pub type Christoffel = [Matrix4<f64>; 4];
+
+// A spacetime in (t, r, theta, phi)-like coordinates
+pub trait Metric: Clone + Send + Sync + 'static {
+    // === Needed ==
+    fn g(&self, pos: &Vector4<f64>) -> Matrix4<f64>;
+
+    fn gamma(&self, pos: &Vector4<f64>) -> Christoffel;
+
+    // Radius below which rays are considered captured
+    fn horizon(&self) -> f64;
+
+    // Mass in geometric units
+    fn mass(&self) -> f64;
+
+
+    // == Optional ==
+    // Length over which the spacetime curves, the mass of a hole. Step sizes
+    // and escape radii scale with twice it, as they do with r_s.
+    fn length_scale(&self) -> f64 {
+        self.mass()
+    }
+
+    // Areal radius at `pos`, the r of spherical coordinates far away
+    fn radius(&self, pos: &Vector4<f64>) -> f64 {
+        pos[1]
+    }
+
+    // Radius of the sphere of static observers that see the sky, where rays
+    // going out stop. Infinite for asymptotically flat spacetimes, where the
+    // sky is taken far enough from the hole instead.
+    fn sky_radius(&self) -> f64 {
+        f64::INFINITY
+    }
+
+    fn g_inv(&self, pos: &Vector4<f64>) -> Matrix4<f64> {
+        self.g(pos).try_inverse().unwrap_or_else(|| Matrix4::from_element(f64::NAN))
+    }
+
+    // Fixes the time component of `v` so that it is a null vector
+    fn null_norm(&self, pos: &Vector4<f64>, v: &mut Vector4<f64>) {
+        physics::null_norm(&self.g(pos), v);
+    }
+
+    // Partial derivatives of the metric, dg[lambda][(mu, nu)] = d_lambda g_mu_nu,
+    // recovered from the Christoffel symbols by lowering their index
+    fn dg(&self, pos: &Vector4<f64>) -> [Matrix4<f64>; 4] {
+        let g = self.g(pos);
+        let gamma = self.gamma(pos);
+        let lowered: Vec<_> = (0..4).map(|sigma| {
+            (0..4).fold(Matrix4::zeros(), |acc, kappa| acc + gamma[kappa] * g[(sigma, kappa)])
+        }).collect();
+
+        let mut dg = [Matrix4::zeros(); 4];
+        for (lambda, dg_lambda) in dg.iter_mut().enumerate() {
+            *dg_lambda = Matrix4::from_fn(|mu, nu| lowered[nu][(lambda, mu)] + lowered[mu][(lambda, nu)]);
+        }
+        dg
+    }
+
+    // Angular velocity dphi/dt of a prograde circular orbit of radius `r` on
+    // the equatorial plane
+    fn keplerian_omega(&self, r: f64) -> f64 {
+        let pos = Vector4::new(0.0, r, std::f64::consts::FRAC_PI_2, 0.0);
+        let dr = self.dg(&pos)[1];
+        let (dt_tt, dt_tphi, dt_phiphi) = (dr[(0, 0)], dr[(0, 3)], dr[(3, 3)]);
+
+        (-dt_tphi + (dt_tphi.powi(2) - dt_tt*dt_phiphi).sqrt()) / dt_phiphi
+    }
+
+    // 4-velocity of an observer at rest at `pos`. Inside an ergoregion or a
+    // horizon, where nothing can be at rest, the observer moving orthogonally
+    // to the surfaces of constant t is used, e.g. the zero angular momentum
+    // observer of Kerr or the free falling one of Painlevé-Gullstrand.
+    fn static_observer(&self, pos: &Vector4<f64>) -> Vector4<f64> {
+        let g = self.g(pos);
+        if g[(0, 0)] < 0.0 {
+            return Vector4::new(1.0/(-g[(0, 0)]).sqrt(), 0.0, 0.0, 0.0);
+        }
+
+        let g_inv = self.g_inv(pos);
+        -g_inv.column(0) / (-g_inv[(0, 0)]).sqrt()
+    }
+
+    // Observer falling in radially from rest at infinity, with u_t = -1 and no
+    // angular momentum
+    fn free_fall_observer(&self, pos: &Vector4<f64>) -> Vector4<f64> {
+        let g_inv = self.g_inv(pos);
+
+        // g^μν u_μ u_ν = -1 with u_μ = (-1, u_r, 0, 0), falling in
+        let (a, b, c) = (g_inv[(1, 1)], -2.0*g_inv[(0, 1)], g_inv[(0, 0)] + 1.0);
+        let disc = (b.powi(2) - 4.0*a*c).max(0.0).sqrt();
+        let u_r = [(-b + disc)/(2.0*a), (-b - disc)/(2.0*a)];
+        let radial = |u_r: f64| -g_inv[(1, 0)] + g_inv[(1, 1)]*u_r;
+        let u_r = if radial(u_r[0]) < radial(u_r[1]) { u_r[0] } else { u_r[1] };
+
+        g_inv * Vector4::new(-1.0, u_r, 0.0, 0.0)
+    }
+
+    // Observer orbiting at the Keplerian angular velocity of the radius of
+    // `pos`, on the circular orbit through it that moves along phi. Off the
+    // equator that orbit is inclined, which is exact only around holes that
+    // don't drag frames. Inside the photon sphere, where no such orbit exists,
+    // the one at rest is used.
+    fn circular_observer(&self, pos: &Vector4<f64>) -> Vector4<f64> {
+        let omega = self.keplerian_omega(pos[1]) / pos[2].sin();
+        let g = self.g(pos);
+        let norm = -(g[(0, 0)] + 2.0*omega*g[(0, 3)] + omega.powi(2)*g[(3, 3)]);
+        if norm <= 0.0 {
+            return self.static_observer(pos);
+        }
+
+        Vector4::new(1.0, 0.0, 0.0, omega) / norm.sqrt()
+    }
+
+    // Orthonormal frame of the observer with 4-velocity `u`: u followed by the
+    // directions of r, theta and phi made orthogonal to it and to each other
+    fn tetrad(&self, pos: &Vector4<f64>, u: &Vector4<f64>) -> [Vector4<f64>; 4] {
+        let g = self.g(pos);
+        let dot = |a: &Vector4<f64>, b: &Vector4<f64>| (a.transpose() * g * b)[0];
+
+        let mut e = [*u, Vector4::y(), Vector4::z(), Vector4::w()];
+        for i in 1..4 {
+            for j in 0..i {
+                // e_0 is timelike, of norm -1
+                let projection = dot(&e[i], &e[j]) * if j == 0 { -1.0 } else { 1.0 };
+                e[i] -= e[j] * projection;
+            }
+            e[i] /= dot(&e[i], &e[i]).sqrt();
+        }
+        e
+    }
+
+    // d²x^λ/dt² = -Γ^λ_μν dx^μ/dt dx^ν/dt
+    fn geodesic_acc(&self, pos: &Vector4<f64>, v: &Vector4<f64>) -> Vector4<f64> {
+        let gamma = self.gamma(pos);
+        Vector4::from_fn(|lambda, _| -(v.transpose() * gamma[lambda] * v)[0])
+    }
+}
+
+// Observers the camera can move with
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Observer {
+    Static,
+    FreeFall,
+    Circular,
+}
+
+impl Observer {
+    // Names as accepted by the CLI
+    pub fn from_name(name: &str) -> Option<Observer> {
+        match name {
+            "static" => Some(Observer::Static),
+            "free-fall" => Some(Observer::FreeFall),
+            "circular" => Some(Observer::Circular),
+            _ => None,
+        }
+    }
+
+    pub fn velocity<M: Metric>(&self, metric: &M, pos: &Vector4<f64>) -> Vector4<f64> {
+        match self {
+            Self::Static => metric.static_observer(pos),
+            Self::FreeFall => metric.free_fall_observer(pos),
+            Self::Circular => metric.circular_observer(pos),
+        }
+    }
+}
+
+// A spacetime given only by its metric components. The Christoffel symbols
+// are computed from them by forward mode automatic differentiation.
+pub trait MetricComponents: Clone + Send + Sync + 'static {
+    fn components<T: Scalar>(&self, pos: &[T; 4]) -> [[T; 4]; 4];
+
+    // Radius below which rays are considered captured
+    fn horizon(&self) -> f64;
+
+    // Mass in geometric units
+    fn mass(&self) -> f64;
+
+    fn length_scale(&self) -> f64 {
+        self.mass()
+    }
+
+    fn radius(&self, pos: &Vector4<f64>) -> f64 {
+        pos[1]
+    }
+
+    fn sky_radius(&self) -> f64 {
+        f64::INFINITY
+    }
+}
+
+impl<C> Metric for C where
+    C: MetricComponents
+{
+    fn g(&self, pos: &Vector4<f64>) -> Matrix4<f64> {
+        let g = self.components(&[pos[0], pos[1], pos[2], pos[3]]);
+        Matrix4::from_fn(|mu, nu| g[mu][nu])
+    }
+
+    fn gamma(&self, pos: &Vector4<f64>) -> Christoffel {
+        christoffel(&self.g_inv(pos), &self.dg(pos))
+    }
+
+    fn dg(&self, pos: &Vector4<f64>) -> [Matrix4<f64>; 4] {
+        let mut dg = [Matrix4::zeros(); 4];
+        for (lambda, dg_lambda) in dg.iter_mut().enumerate() {
+            let mut x = [Dual::constant(pos[0]), Dual::constant(pos[1]), Dual::constant(pos[2]), Dual::constant(pos[3])];
+            x[lambda] = Dual::variable(pos[lambda]);
+
+            let g = self.components(&x);
+            *dg_lambda = Matrix4::from_fn(|mu, nu| g[mu][nu].eps);
+        }
+        dg
+    }
+
+    fn horizon(&self) -> f64 {
+        MetricComponents::horizon(self)
+    }
+
+    fn mass(&self) -> f64 {
+        MetricComponents::mass(self)
+    }
+
+    fn length_scale(&self) -> f64 {
+        MetricComponents::length_scale(self)
+    }
+
+    fn radius(&self, pos: &Vector4<f64>) -> f64 {
+        MetricComponents::radius(self, pos)
+    }
+
+    fn sky_radius(&self) -> f64 {
+        MetricComponents::sky_radius(self)
+    }
+}
+
+// Christoffel symbols from the inverse metric and the partial derivatives of
+// the metric, dg[lambda][(mu, nu)] = d_lambda g_mu_nu
+pub fn christoffel(g_inv: &Matrix4<f64>, dg: &[Matrix4<f64>; 4]) -> Christoffel {
+    let mut gamma = [Matrix4::zeros(); 4];
+    for (lambda, gamma_lambda) in gamma.iter_mut().enumerate() {
+        for mu in 0..4 {
+            for nu in mu..4 {
+                let mut s = 0.0;
+                for sigma in 0..4 {
+                    s += g_inv[(lambda, sigma)]*(
+                        dg[mu][(sigma, nu)] + dg[nu][(sigma, mu)] - dg[sigma][(mu, nu)]
+                    );
+                }
+                gamma_lambda[(mu, nu)] = s/2.0;
+                gamma_lambda[(nu, mu)] = s/2.0;
+            }
+        }
+    }
+    gamma
+}
+
+// Checks the Christoffel symbols of `metric` against those of `expected` at
+// `pos`, for the metrics that reduce to another one
+#[cfg(test)]
+pub fn assert_christoffel_matches<M: Metric, E: Metric>(metric: &M, expected: &E, pos: &Vector4<f64>) {
+    let (gamma, expected) = (metric.gamma(pos), expected.gamma(pos));
+    for lambda in 0..4 {
+        for mu in 0..4 {
+            for nu in 0..4 {
+                let e = expected[lambda][(mu, nu)];
+                assert!(
+                    (gamma[lambda][(mu, nu)] - e).abs() < 1e-9 * (1.0 + e.abs()),
+                    "Failed at {:?}",
+                    (lambda, mu, nu, pos)
+                );
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use rand::prelude::*;
+    use nalgebra as na;
+
+    // Schwarzschild with r_s = 1, from its components only
+    #[derive(Clone)]
+    struct AutoSchwarzschild;
+
+    impl MetricComponents for AutoSchwarzschild {
+        fn components<T: Scalar>(&self, pos: &[T; 4]) -> [[T; 4]; 4] {
+            let zero = T::from(0.0);
+            let (r, theta) = (pos[1], pos[2]);
+            let f = -(r.powi(-1) - 1.0);
+
+            [
+                [-f,   zero,        zero,      zero],
+                [zero, f.powi(-1), zero,      zero],
+                [zero, zero,        r.powi(2), zero],
+                [zero, zero,        zero,      (r * theta.sin()).powi(2)],
+            ]
+        }
+
+        fn horizon(&self) -> f64 {
+            1.0
+        }
+
+        fn mass(&self) -> f64 {
+            0.5
+        }
+    }
+
+    #[test]
+    fn autodiff_gamma_is_schwarzschild() {
+        let pos = na::Vector4::<f64>::new(random(), random(), random(), random());
+        let pos = 100.0 * pos + na::Vector4::new(0.0, 1.01, 0.1, 0.0);
+
+        assert_christoffel_matches(&AutoSchwarzschild, &Schwarzschild::new(0.5), &pos);
+    }
+
+    #[test]
+    fn observers_are_normalized() {
+        let pos = na::Vector4::new(0.0, 2.0 + 20.0 * random::<f64>(), 0.1 + 3.0 * random::<f64>(), random());
+        let kerr = Kerr::new(0.5, 2.0 * random::<f64>() - 1.0);
+
+        for &observer in &[Observer::Static, Observer::FreeFall, Observer::Circular] {
+            let u = observer.velocity(&kerr, &pos);
+            let norm = (u.transpose() * kerr.g(&pos) * u)[0];
+            assert!((norm + 1.0).abs() < 1e-9 && u[0] > 0.0, "Failed for {:?} at {:?}", observer, pos);
+        }
+
+        // Falling at the Newtonian escape velocity in proper time
+        let u = Observer::FreeFall.velocity(&Schwarzschild::new(0.5), &pos);
+        assert!((u[1] + (1.0/pos[1]).sqrt()).abs() < 1e-9);
+    }
+
+    #[test]
+    fn inclined_circular_orbit() {
+        let metric = Schwarzschild::new(0.5);
+        let pos = na::Vector4::new(0.0, 1.6 + 20.0 * random::<f64>(), 0.2 + 2.7 * random::<f64>(), 0.0);
+
+        // At the top of the orbit, where it neither climbs nor falls
+        let u = Observer::Circular.velocity(&metric, &pos);
+        assert!(metric.geodesic_acc(&pos, &u)[1].abs() < 1e-9, "Failed at {:?}", pos);
+    }
+
+    #[test]
+    fn free_fall_aberration() {
+        let metric = Schwarzschild::new(0.5);
+        let pos = na::Vector4::new(0.0, 1.5 + 20.0 * random::<f64>(), 1.0, 0.0);
+        let g = metric.g(&pos);
+        let dot = |a: &na::Vector4<f64>, b: &na::Vector4<f64>| (a.transpose() * g * b)[0];
+
+        let e = metric.tetrad(&pos, &metric.free_fall_observer(&pos));
+        let e_static = metric.tetrad(&pos, &metric.static_observer(&pos));
+
+        // Ray looking at an angle from the outward direction, in both frames
+        let cos = 2.0 * random::<f64>() - 1.0;
+        let k = -e[0] + e[1] * cos + e[2] * (1.0 - cos.powi(2)).sqrt();
+        let cos_static = dot(&k, &e_static[1]) / dot(&k, &e_static[0]);
+
+        // Relativistic aberration for a speed of sqrt(r_s/r) towards the hole
+        let beta = (1.0/pos[1]).sqrt();
+        let expected = (cos + beta) / (1.0 + beta * cos);
+        assert!((cos_static - expected).abs() < 1e-9, "Failed at {:?}", (pos[1], cos));
+    }
+}
@@ -0,0 +1,90 @@
+use crate::autodiff::Scalar;
+
+use super::*;
+
+// Schwarzschild in Painlevé-Gullstrand coordinates (T, r, theta, phi), where
+// T is the proper time of observers falling in from rest at infinity:
+// ds^2 = -dT^2 + (dr + sqrt(2M/r) dT)^2 + r^2 dΩ^2
+// Nothing is singular at the horizon, so rays and the camera can cross it.
+#[derive(Clone, Copy, Debug)]
+pub struct PainleveGullstrand {
+    pub mass: f64,
+}
+
+impl PainleveGullstrand {
+    pub fn new(mass: f64) -> PainleveGullstrand {
+        PainleveGullstrand {mass}
+    }
+}
+
+impl MetricComponents for PainleveGullstrand {
+    fn components<T: Scalar>(&self, pos: &[T; 4]) -> [[T; 4]; 4] {
+        let zero = T::from(0.0);
+        let (r, theta) = (pos[1], pos[2]);
+        // Velocity of the free falling observers
+        let v = (r.powi(-1) * (2.0 * self.mass)).sqrt();
+
+        [
+            [v.powi(2) - 1.0, v,            zero,      zero],
+            [v,               T::from(1.0), zero,      zero],
+            [zero,            zero,         r.powi(2), zero],
+            [zero,            zero,         zero,      (r * theta.sin()).powi(2)],
+        ]
+    }
+
+    // Rays traced back from outside only approach the horizon as T goes to
+    // minus infinity, those traced from inside cross it outwards
+    fn horizon(&self) -> f64 {
+        2.0 * self.mass
+    }
+
+    fn mass(&self) -> f64 {
+        self.mass
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use rand::prelude::*;
+
+    use crate::integrator::{self, Integrator, Rk4};
+
+    #[test]
+    fn free_fall_observer_inside_horizon() {
+        let metric = PainleveGullstrand::new(0.5);
+        let pos = Vector4::new(0.0, 0.1 + 0.8 * random::<f64>(), 1.0, 0.0);
+
+        // Falls at the Newtonian escape velocity, with T as its proper time
+        let u = metric.static_observer(&pos);
+        assert!((u - Vector4::new(1.0, -(1.0/pos[1]).sqrt(), 0.0, 0.0)).norm() < 1e-9, "Failed at {}", pos[1]);
+
+        let e = metric.tetrad(&pos, &u);
+        let g = metric.g(&pos);
+        for i in 0..4 {
+            for j in 0..4 {
+                let expected = if i != j { 0.0 } else if i == 0 { -1.0 } else { 1.0 };
+                assert!(((e[i].transpose() * g * e[j])[0] - expected).abs() < 1e-9, "Failed at {:?}", (i, j));
+            }
+        }
+    }
+
+    #[test]
+    fn rays_traced_back_leave_the_horizon() {
+        let metric = PainleveGullstrand::new(0.5);
+        let pos = Vector4::new(0.0, 0.5 + 0.4 * random::<f64>(), std::f64::consts::FRAC_PI_2, 0.0);
+
+        // Looking outwards, the light comes from outside
+        let e = metric.tetrad(&pos, &metric.static_observer(&pos));
+        let dir = -e[0] + e[1];
+        let mut y = integrator::state(&pos, &dir);
+        for _ in 0..10_000 {
+            Rk4.step(&metric, &mut y, 1e-3);
+            if integrator::pos(&y)[1] > 1.5 {
+                break;
+            }
+        }
+        assert!(integrator::pos(&y)[1] > 1.5, "Failed at {}", pos[1]);
+    }
+}
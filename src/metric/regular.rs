@@ -0,0 +1,157 @@
+use crate::autodiff::Scalar;
+
+use super::*;
+
+// Hayward regular black hole of mass `mass`, whose core of size `l` replaces
+// the singularity with a patch of de Sitter:
+// ds^2 = -f dt^2 + dr^2/f + r^2 dΩ^2 with f = 1 - 2Mr^2/(r^3 + 2Ml^2)
+#[derive(Clone, Copy, Debug)]
+pub struct Hayward {
+    pub mass: f64,
+    pub l: f64,
+    horizon: f64,
+}
+
+impl Hayward {
+    // `length` is the dimensionless l/M, with a horizon below 4/(3 sqrt 3)
+    pub fn new(mass: f64, length: f64) -> Hayward {
+        let mut hayward = Hayward {mass, l: length * mass, horizon: 0.0};
+        hayward.horizon = outer_horizon(mass, |r| hayward.lapse(r));
+        hayward
+    }
+
+    // Whether there is a horizon hiding the core
+    pub fn is_hole(&self) -> bool {
+        self.horizon > 0.0
+    }
+
+    pub fn lapse<T: Scalar>(&self, r: T) -> T {
+        let m = self.mass;
+        -(r.powi(2) * (2.0*m) / (r.powi(3) + 2.0*m*self.l.powi(2))) + 1.0
+    }
+}
+
+impl MetricComponents for Hayward {
+    fn components<T: Scalar>(&self, pos: &[T; 4]) -> [[T; 4]; 4] {
+        lapse_components(self.lapse(pos[1]), pos)
+    }
+
+    fn horizon(&self) -> f64 {
+        self.horizon
+    }
+
+    fn mass(&self) -> f64 {
+        self.mass
+    }
+}
+
+// Bardeen regular black hole of mass `mass`, with the magnetic monopole
+// charge `g` of its nonlinear electrodynamics source:
+// ds^2 = -f dt^2 + dr^2/f + r^2 dΩ^2 with f = 1 - 2Mr^2/(r^2 + g^2)^(3/2)
+#[derive(Clone, Copy, Debug)]
+pub struct Bardeen {
+    pub mass: f64,
+    pub g: f64,
+    horizon: f64,
+}
+
+impl Bardeen {
+    // `length` is the dimensionless g/M, with a horizon below 4/(3 sqrt 3)
+    pub fn new(mass: f64, length: f64) -> Bardeen {
+        let mut bardeen = Bardeen {mass, g: length * mass, horizon: 0.0};
+        bardeen.horizon = outer_horizon(mass, |r| bardeen.lapse(r));
+        bardeen
+    }
+
+    // Whether there is a horizon hiding the core
+    pub fn is_hole(&self) -> bool {
+        self.horizon > 0.0
+    }
+
+    pub fn lapse<T: Scalar>(&self, r: T) -> T {
+        -(r.powi(2) * (2.0*self.mass) / (r.powi(2) + self.g.powi(2)).powf(1.5)) + 1.0
+    }
+}
+
+impl MetricComponents for Bardeen {
+    fn components<T: Scalar>(&self, pos: &[T; 4]) -> [[T; 4]; 4] {
+        lapse_components(self.lapse(pos[1]), pos)
+    }
+
+    fn horizon(&self) -> f64 {
+        self.horizon
+    }
+
+    fn mass(&self) -> f64 {
+        self.mass
+    }
+}
+
+// Static, spherically symmetric metric with g_tt = -f and g_rr = 1/f
+fn lapse_components<T: Scalar>(f: T, pos: &[T; 4]) -> [[T; 4]; 4] {
+    let zero = T::from(0.0);
+    let (r, theta) = (pos[1], pos[2]);
+
+    [
+        [-f,   zero,       zero,      zero],
+        [zero, f.powi(-1), zero,      zero],
+        [zero, zero,       r.powi(2), zero],
+        [zero, zero,       zero,      (r * theta.sin()).powi(2)],
+    ]
+}
+
+// Largest root of the lapse `f`, below r_s for these holes. Without one the
+// core is visible and nothing is captured. Found by a scan, so only once in
+// the constructors.
+fn outer_horizon<F: Fn(f64) -> f64>(mass: f64, f: F) -> f64 {
+    let steps = 1000;
+    let h = 2.0 * mass / steps as f64;
+    for i in (0..steps).rev() {
+        let (mut lo, mut hi) = (i as f64 * h, (i + 1) as f64 * h);
+        if f(lo) > 0.0 {
+            continue;
+        }
+
+        for _ in 0..60 {
+            let mid = 0.5 * (lo + hi);
+            if f(mid) > 0.0 {
+                hi = mid;
+            } else {
+                lo = mid;
+            }
+        }
+        return 0.5 * (lo + hi);
+    }
+    0.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use rand::prelude::*;
+
+    #[test]
+    fn gamma_without_core_is_schwarzschild() {
+        let pos = Vector4::<f64>::new(random(), random(), random(), random());
+        let pos = 100.0 * pos + Vector4::new(0.0, 1.01, 0.1, 0.0);
+
+        assert_christoffel_matches(&Hayward::new(0.5, 0.0), &Schwarzschild::new(0.5), &pos);
+        assert_christoffel_matches(&Bardeen::new(0.5, 0.0), &Schwarzschild::new(0.5), &pos);
+    }
+
+    #[test]
+    fn horizon_shrinks_then_vanishes() {
+        let length = 0.7 * random::<f64>();
+        let (hayward, bardeen) = (Hayward::new(0.5, length), Bardeen::new(0.5, length));
+        let (r_hayward, r_bardeen) = (Metric::horizon(&hayward), Metric::horizon(&bardeen));
+
+        assert!(r_hayward > 0.0 && r_hayward <= 1.0 && hayward.lapse(r_hayward).abs() < 1e-9, "Failed at {}", length);
+        assert!(r_bardeen > 0.0 && r_bardeen <= 1.0 && bardeen.lapse(r_bardeen).abs() < 1e-9, "Failed at {}", length);
+
+        assert!(hayward.is_hole() && bardeen.is_hole(), "Failed at {}", length);
+
+        // Past the extremal 4/(3 sqrt 3) ~ 0.77 the core is naked
+        assert!(!Hayward::new(0.5, 0.8).is_hole() && !Bardeen::new(0.5, 0.8).is_hole());
+    }
+}
@@ -0,0 +1,91 @@
+use crate::autodiff::Scalar;
+
+use super::*;
+
+// Reissner-Nordström metric of a charged, non rotating hole of mass `mass`
+// and charge `q` in geometric units:
+// ds^2 = -f dt^2 + dr^2/f + r^2 dΩ^2 with f = 1 - 2M/r + Q^2/r^2
+#[derive(Clone, Copy, Debug)]
+pub struct ReissnerNordstrom {
+    pub mass: f64,
+    pub q: f64,
+}
+
+impl ReissnerNordstrom {
+    // `charge` is the dimensionless Q/M, between -1 and 1 for a hole
+    pub fn new(mass: f64, charge: f64) -> ReissnerNordstrom {
+        ReissnerNordstrom {mass, q: charge * mass}
+    }
+
+    // Inner and outer horizons, where f vanishes. Both are at M once the hole
+    // is extremal.
+    pub fn horizons(&self) -> (f64, f64) {
+        let (m, q) = (self.mass, self.q);
+        let outer = m + (m.powi(2) - q.powi(2)).max(0.0).sqrt();
+        // r- r+ = Q^2, without the cancellation of M - sqrt(M^2 - Q^2)
+        (q.powi(2) / outer, outer)
+    }
+
+    // Radius of the circular photon orbits, 3M without charge and 2M for an
+    // extremal hole
+    pub fn photon_sphere(&self) -> f64 {
+        let (m, q) = (self.mass, self.q);
+        (3.0*m + (9.0*m.powi(2) - 8.0*q.powi(2)).max(0.0).sqrt()) / 2.0
+    }
+}
+
+impl MetricComponents for ReissnerNordstrom {
+    fn components<T: Scalar>(&self, pos: &[T; 4]) -> [[T; 4]; 4] {
+        let zero = T::from(0.0);
+        let (r, theta) = (pos[1], pos[2]);
+        let f = r.powi(-1) * (-2.0 * self.mass) + r.powi(-2) * self.q.powi(2) + 1.0;
+
+        [
+            [-f,   zero,       zero,      zero],
+            [zero, f.powi(-1), zero,      zero],
+            [zero, zero,       r.powi(2), zero],
+            [zero, zero,       zero,      (r * theta.sin()).powi(2)],
+        ]
+    }
+
+    // Outer horizon
+    fn horizon(&self) -> f64 {
+        self.horizons().1
+    }
+
+    fn mass(&self) -> f64 {
+        self.mass
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use rand::prelude::*;
+
+    #[test]
+    fn gamma_without_charge_is_schwarzschild() {
+        let pos = Vector4::<f64>::new(random(), random(), random(), random());
+        let pos = 100.0 * pos + Vector4::new(0.0, 1.01, 0.1, 0.0);
+
+        assert_christoffel_matches(&ReissnerNordstrom::new(0.5, 0.0), &Schwarzschild::new(0.5), &pos);
+    }
+
+    #[test]
+    fn horizons_and_photon_sphere() {
+        let rn = ReissnerNordstrom::new(0.5, 2.0 * random::<f64>() - 1.0);
+        let g = |r: f64| rn.g(&Vector4::new(0.0, r, std::f64::consts::FRAC_PI_2, 0.0));
+
+        let (inner, outer) = rn.horizons();
+        assert!(g(inner)[(0, 0)].abs() < 1e-9 && g(outer)[(0, 0)].abs() < 1e-9, "Failed at {}", rn.q);
+
+        // The Keplerian orbit is null there
+        let r = rn.photon_sphere();
+        let omega = rn.keplerian_omega(r);
+        assert!((g(r)[(0, 0)] + omega.powi(2) * g(r)[(3, 3)]).abs() < 1e-9, "Failed at {}", rn.q);
+
+        // and shrinks as the charge grows
+        assert!(r <= 1.5 && ReissnerNordstrom::new(0.5, 1.1 * rn.q / rn.mass).photon_sphere() <= r, "Failed at {}", rn.q);
+    }
+}
@@ -0,0 +1,57 @@
+use nalgebra::{Matrix4, Vector4};
+
+use crate::physics;
+
+use super::*;
+
+// Schwarzschild metric of mass `mass`, so r_s = 2 * mass
+#[derive(Clone, Copy, Debug)]
+pub struct Schwarzschild {
+    pub mass: f64,
+}
+
+impl Schwarzschild {
+    pub fn new(mass: f64) -> Schwarzschild {
+        Schwarzschild {mass}
+    }
+
+    pub fn rs(&self) -> f64 {
+        2.0 * self.mass
+    }
+}
+
+impl Default for Schwarzschild {
+    // r_s = 1
+    fn default() -> Schwarzschild {
+        Schwarzschild::new(0.5)
+    }
+}
+
+impl Metric for Schwarzschild {
+    fn g(&self, pos: &Vector4<f64>) -> Matrix4<f64> {
+        Matrix4::from_fn(|mu, nu| physics::g(mu, nu, self.rs())(pos))
+    }
+
+    fn gamma(&self, pos: &Vector4<f64>) -> Christoffel {
+        let mut gamma = [Matrix4::zeros(); 4];
+        for (lambda, gamma_lambda) in gamma.iter_mut().enumerate() {
+            *gamma_lambda = Matrix4::from_fn(|mu, nu| physics::gamma(lambda, mu, nu, self.rs())(pos));
+        }
+        gamma
+    }
+
+    fn horizon(&self) -> f64 {
+        self.rs()
+    }
+
+    fn mass(&self) -> f64 {
+        self.mass
+    }
+
+    fn null_norm(&self, pos: &Vector4<f64>, v: &mut Vector4<f64>) {
+        // Rays traced back in time keep going back
+        let sign = v[0].signum();
+        physics::time_norm(pos, v, self.rs());
+        v[0] *= sign;
+    }
+}
@@ -0,0 +1,120 @@
+use std::f64::consts::PI;
+
+use crate::autodiff::Scalar;
+
+use super::*;
+
+// Schwarzschild-de Sitter (Kottler) metric of mass `mass` with a cosmological
+// constant `lambda` in geometric units:
+// ds^2 = -f dt^2 + dr^2/f + r^2 dΩ^2 with f = 1 - 2M/r - Λr^2/3
+// There is nothing at infinity, static observers only exist between the
+// event horizon and the cosmological one.
+#[derive(Clone, Copy, Debug)]
+pub struct SchwarzschildDeSitter {
+    pub mass: f64,
+    pub lambda: f64,
+    sky: f64,
+}
+
+impl SchwarzschildDeSitter {
+    // `lambda` is the dimensionless ΛM^2, below 1/9 for two horizons
+    pub fn new(mass: f64, lambda: f64) -> SchwarzschildDeSitter {
+        let mut sds = SchwarzschildDeSitter {mass, lambda: lambda / mass.powi(2), sky: 0.0};
+        sds.sky = sds.static_radius();
+        sds
+    }
+
+    // The sky stays at the static radius unless the camera at `r` is farther
+    // out, then it moves halfway from the camera to the cosmological horizon
+    pub fn set_camera_radius(&mut self, r: f64) {
+        let cosmological = self.horizons().1;
+        self.sky = if r < self.static_radius() { self.static_radius() } else { (r + cosmological) / 2.0 };
+    }
+
+    // Event and cosmological horizons, the positive roots of f. Written with
+    // asin so that the event horizon goes smoothly to 2M as Λ goes to 0.
+    pub fn horizons(&self) -> (f64, f64) {
+        let (m, l) = (self.mass, self.lambda);
+        let angle = (3.0 * m * l.sqrt()).min(1.0).asin() / 3.0;
+        let scale = 2.0 / l.sqrt();
+        (scale * angle.sin(), scale * (PI/6.0 + angle).cos())
+    }
+
+    // Radius where the attraction of the hole balances the expansion, so that
+    // static observers there are in free fall
+    pub fn static_radius(&self) -> f64 {
+        (3.0 * self.mass / self.lambda).cbrt()
+    }
+}
+
+impl MetricComponents for SchwarzschildDeSitter {
+    fn components<T: Scalar>(&self, pos: &[T; 4]) -> [[T; 4]; 4] {
+        let zero = T::from(0.0);
+        let (r, theta) = (pos[1], pos[2]);
+        let f = r.powi(-1) * (-2.0 * self.mass) - r.powi(2) * (self.lambda / 3.0) + 1.0;
+
+        [
+            [-f,   zero,       zero,      zero],
+            [zero, f.powi(-1), zero,      zero],
+            [zero, zero,       r.powi(2), zero],
+            [zero, zero,       zero,      (r * theta.sin()).powi(2)],
+        ]
+    }
+
+    // Event horizon
+    fn horizon(&self) -> f64 {
+        self.horizons().0
+    }
+
+    fn mass(&self) -> f64 {
+        self.mass
+    }
+
+    // The sky is seen by the free falling static observers, the closest thing
+    // to observers at infinity, unless the camera is beyond them
+    fn sky_radius(&self) -> f64 {
+        self.sky
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use rand::prelude::*;
+
+    #[test]
+    fn horizons_are_roots() {
+        let sds = SchwarzschildDeSitter::new(0.5, random::<f64>() / 9.0);
+        let g_tt = |r: f64| sds.g(&Vector4::new(0.0, r, 1.0, 0.0))[(0, 0)];
+
+        let (event, cosmological) = sds.horizons();
+        assert!((1.0..1.5).contains(&event) && cosmological > 1.5, "Failed at {}", sds.lambda);
+        assert!(g_tt(event).abs() < 1e-9 && g_tt(cosmological).abs() < 1e-9, "Failed at {}", sds.lambda);
+
+        // Between them, where g_tt is the largest
+        let r = sds.static_radius();
+        assert!(r > event && r < cosmological && g_tt(r) < 0.0, "Failed at {}", sds.lambda);
+        assert!(sds.dg(&Vector4::new(0.0, r, 1.0, 0.0))[1][(0, 0)].abs() < 1e-9, "Failed at {}", sds.lambda);
+    }
+
+    #[test]
+    fn sky_beyond_camera() {
+        let mut sds = SchwarzschildDeSitter::new(0.5, 1e-3);
+        let (_, cosmological) = sds.horizons();
+        for &r in &[5.0, sds.static_radius(), 10.0, cosmological - 1.0] {
+            sds.set_camera_radius(r);
+            let sky = Metric::sky_radius(&sds);
+            assert!(sky >= r && sky >= sds.static_radius() && sky < cosmological, "Failed at {}", r);
+        }
+    }
+
+    #[test]
+    fn small_lambda_horizons() {
+        for &lambda in &[1e-6, 1e-12, 1e-20] {
+            let (event, cosmological) = SchwarzschildDeSitter::new(0.5, lambda).horizons();
+            assert!((event - 1.0).abs() < 1e-5, "Failed at {}", lambda);
+            assert!((cosmological * lambda.sqrt() / 0.5 / 3f64.sqrt() - 1.0).abs() < 1e-2, "Failed at {}", lambda);
+        }
+    }
+}
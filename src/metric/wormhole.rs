@@ -0,0 +1,90 @@
+use crate::autodiff::Scalar;
+
+use super::*;
+
+// Ellis-Bronnikov wormhole, the Morris-Thorne wormhole without tidal forces
+// and with shape function b(r) = b0^2/r, in proper radial distance l:
+// ds^2 = -dt^2 + dl^2 + (b0^2 + l^2) dΩ^2
+// The throat of radius b0 is at l = 0, the other side at negative l.
+#[derive(Clone, Copy, Debug)]
+pub struct MorrisThorne {
+    pub throat: f64,
+}
+
+impl MorrisThorne {
+    pub fn new(throat: f64) -> MorrisThorne {
+        MorrisThorne {throat}
+    }
+}
+
+impl MetricComponents for MorrisThorne {
+    fn components<T: Scalar>(&self, pos: &[T; 4]) -> [[T; 4]; 4] {
+        let (zero, one) = (T::from(0.0), T::from(1.0));
+        let (l, theta) = (pos[1], pos[2]);
+        let r2 = l.powi(2) + self.throat.powi(2);
+
+        [
+            [-one, zero, zero, zero],
+            [zero, one,  zero, zero],
+            [zero, zero, r2,   zero],
+            [zero, zero, zero, r2 * theta.sin().powi(2)],
+        ]
+    }
+
+    // Nothing is captured, rays go through the throat or back out
+    fn horizon(&self) -> f64 {
+        0.0
+    }
+
+    fn mass(&self) -> f64 {
+        0.0
+    }
+
+    // The throat plays the part of r_s
+    fn length_scale(&self) -> f64 {
+        self.throat / 2.0
+    }
+
+    fn radius(&self, pos: &Vector4<f64>) -> f64 {
+        pos[1].hypot(self.throat)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use rand::prelude::*;
+
+    use crate::integrator::{self, Integrator, Rk4};
+
+    // Final l of the ray leaving l = 10 towards the throat with impact
+    // parameter `b`
+    fn trace(wormhole: &MorrisThorne, b: f64) -> f64 {
+        let pos = Vector4::new(0.0, 10.0, std::f64::consts::FRAC_PI_2, 0.0);
+        let r = Metric::radius(wormhole, &pos);
+        let mut dir = Vector4::new(1.0, -(1.0 - (b/r).powi(2)).sqrt(), 0.0, b/r.powi(2));
+        wormhole.null_norm(&pos, &mut dir);
+
+        let mut y = integrator::state(&pos, &dir);
+        for _ in 0..100_000 {
+            let (pos, dir) = (integrator::pos(&y), integrator::dir(&y));
+            if pos[1].abs() > 20.0 {
+                break;
+            }
+            let dt = integrator::ray_step(&Rk4, wormhole, &pos, &dir, 0.0);
+            Rk4.step(wormhole, &mut y, dt);
+        }
+        integrator::pos(&y)[1]
+    }
+
+    #[test]
+    fn rays_inside_the_throat_go_through() {
+        let wormhole = MorrisThorne::new(1.0);
+        let b = random::<f64>();
+
+        // Turning point where b0^2 + l^2 = b^2
+        assert!(trace(&wormhole, 0.95 * b) < -20.0, "Failed at {}", b);
+        assert!(trace(&wormhole, 1.05 + b) > 20.0, "Failed at {}", b);
+    }
+}
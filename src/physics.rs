@@ -1,33 +1,23 @@
-use sdl2::pixels::Color;
-
 use nalgebra as na;
-use na::{Unit, Vector3, Vector4, Rotation3};
+use na::{Unit, Vector3, Vector4, Matrix4};
+
 
+// Schwarzschild metric with Schwarzschild radius `rs`
+pub fn g(mu: usize, nu: usize, rs: f64) -> impl Fn(&Vector4<f64>) -> f64 {
+    move |pos: &Vector4<f64>| {
+        let r = pos[1];
 
-pub fn g(mu: usize, nu: usize) -> impl Fn(&Vector4<f64>) -> f64 {
-    if mu != nu {
-        |_pos: &Vector4<f64>| {
-            0.0
+        match (mu, nu) {
+            (0, 0) => -(1.0 - rs/r),
+            (1, 1) => 1.0/(1.0 - rs/r),
+            (2, 2) => r.powf(2.0),
+            (3, 3) => (r * pos[2].sin()).powf(2.0),
+            _ => 0.0,
         }
-    } else {
-        [
-            |pos: &Vector4<f64>| {
-                -(1.0 - 1.0/pos[1])
-            },
-            |pos: &Vector4<f64>| {
-                1.0/(1.0 - 1.0/pos[1])
-            },
-            |pos: &Vector4<f64>| {
-                pos[1].powf(2.0)
-            },
-            |pos: &Vector4<f64>| {
-                pos[1].powf(2.0) * pos[2].sin()
-            },
-        ][mu]
     }
 }
 
-pub fn gamma(lambda: usize, mu: usize, nu: usize) -> impl Fn(&Vector4<f64>) -> f64 {
+pub fn gamma(lambda: usize, mu: usize, nu: usize, rs: f64) -> impl Fn(&Vector4<f64>) -> f64 {
     // Symetry
     let (mu, nu) = if mu > nu {
         (nu, mu)
@@ -35,90 +25,61 @@ pub fn gamma(lambda: usize, mu: usize, nu: usize) -> impl Fn(&Vector4<f64>) -> f
         (mu, nu)
     };
 
-    let zero = |_pos: &Vector4<f64>| {
-        0.0
-    };
-
-    let inv_r = |pos: &Vector4<f64>| {
-        1.0/pos[1]
-    };
-    
-    match (lambda, mu, nu) {
-        (0, 0, 1) => |pos: &Vector4<f64>| {
-            1.0/(2.0*pos[1]*(pos[1]-1.0))
-        },
-        (1, 0, 0) => |pos: &Vector4<f64>|{
-            (pos[1] - 1.0)/(2.0*pos[1].powf(3.0))
-        },
-        (1, 1, 1) => |pos: &Vector4<f64>| {
-            -1.0/(2.0*pos[1]*(pos[1]-1.0))
-        },
-        (1, 2, 2) => |pos: &Vector4<f64>|{
-            1.0 - pos[1]
-        },
-        (1, 3, 3) => |pos: &Vector4<f64>|{
-            (1.0 - pos[1])*pos[2].sin().powf(2.0)    
-        },
-        (2, 1, 2) => inv_r,
-        (2, 3, 3) => |pos: &Vector4<f64>|{
-            -pos[2].sin()*pos[2].cos()
-        },
-        (3, 1, 3) => inv_r,
-        (3, 2, 3) => |pos: &Vector4<f64>|{
-            1.0/pos[2].tan()
-        },
-        _ => zero,
+    move |pos: &Vector4<f64>| {
+        let (r, theta) = (pos[1], pos[2]);
+
+        match (lambda, mu, nu) {
+            (0, 0, 1) => rs/(2.0*r*(r - rs)),
+            (1, 0, 0) => rs*(r - rs)/(2.0*r.powf(3.0)),
+            (1, 1, 1) => -rs/(2.0*r*(r - rs)),
+            (1, 2, 2) => rs - r,
+            (1, 3, 3) => (rs - r)*theta.sin().powf(2.0),
+            (2, 1, 2) => 1.0/r,
+            (2, 3, 3) => -theta.sin()*theta.cos(),
+            (3, 1, 3) => 1.0/r,
+            (3, 2, 3) => 1.0/theta.tan(),
+            _ => 0.0,
+        }
     }
 }
 
-pub fn get_accretion_disk_color((r, _theta, _phi): (f64, f64, f64)) -> Color {
-    let temperature = 7e3 * r.powf(-3.0/4.0);
-    
-    let scale = 1e6;
-    let intensity = scale/((29622.4/temperature).exp() - 1.0);
+// Fixes the time component of `v` so that it is a null vector of `g`. Of the
+// two solutions, the one closest to the current time component is kept.
+pub fn null_norm(g: &Matrix4<f64>, v: &mut Vector4<f64>) {
+    let a = g[(0, 0)];
+    let mut b = 0.0;
+    let mut c = 0.0;
+    for i in 1..4 {
+        b += 2.0*g[(0, i)]*v[i];
+        for j in 1..4 {
+            c += g[(i, j)]*v[i]*v[j];
+        }
+    }
 
-    let temperature = temperature / 100.0;
+    let disc = (b.powi(2) - 4.0*a*c).max(0.0).sqrt();
+    let t1 = (-b + disc)/(2.0*a);
+    let t2 = (-b - disc)/(2.0*a);
 
-    let r = if temperature <= 66.0 {
-        255.0
+    v[0] = if (t1 - v[0]).abs() < (t2 - v[0]).abs() {
+        t1
     } else {
-        let r = temperature - 60.0;
-        let r = 329.698727446 * r.powf(-0.1332047592);
-        r.clamp(0.0, 255.0)
+        t2
     };
+}
 
-    let g = if temperature <= 6600.0 {
-        let g = temperature;
-        let g = 99.4708025861 * g.ln() - 161.1195681661;
-        g.clamp(0.0, 255.0)
-    } else {
-        let g = temperature - 60.0;
-        let g = 288.1221695283 * g.powf(-0.0755148492);
-        g.clamp(0.0, 255.0)
-    };
+// Orthonormal spherical basis (r_hat, theta_hat, phi_hat) at angles theta and phi
+pub fn sph_basis(theta: f64, phi: f64) -> (Vector3<f64>, Vector3<f64>, Vector3<f64>) {
+    let (st, ct) = theta.sin_cos();
+    let (sp, cp) = phi.sin_cos();
 
-    let b = if temperature >= 66.0 {
-	    255.0
-    } else {
-        if temperature <= 19.0 {
-            0.0
-        } else {
-            let b = temperature - 10.0;
-            let b = 138.5177312231 * b.ln() - 305.0447927307;
-            b.clamp(0.0, 255.0)
-        }
-    };
-    
-    let (r, g, b) = (
-        (r*intensity).clamp(0.0, 255.0),
-        (g*intensity).clamp(0.0, 255.0),
-        (b*intensity).clamp(0.0, 255.0),
-    );
-    
-    Color::RGB(r as u8, g as u8, b as u8)
+    (
+        Vector3::new(st*cp, st*sp, ct),
+        Vector3::new(ct*cp, ct*sp, -st),
+        Vector3::new(-sp, cp, 0.0),
+    )
 }
 
-pub fn time_norm(p: &Vector4<f64>, v: &mut Vector4<f64>) {
+pub fn time_norm(p: &Vector4<f64>, v: &mut Vector4<f64>, rs: f64) {
     let v3 = Vector3::new(v[1], v[2], v[3]).normalize();
     let v3_norm = (
         v[0].powf(2.0) +
@@ -132,10 +93,10 @@ pub fn time_norm(p: &Vector4<f64>, v: &mut Vector4<f64>) {
     v[3] = v3[2];
 
     v[0] = ((
-        g(1,1)(&p)*v[1].powf(2.0) + 
-        g(2,2)(&p)*v[2].powf(2.0) + 
-        g(3,3)(&p)*v[3].powf(2.0)
-    )/(-g(0,0)(&p))).sqrt();
+        g(1,1,rs)(p)*v[1].powf(2.0) + 
+        g(2,2,rs)(p)*v[2].powf(2.0) + 
+        g(3,3,rs)(p)*v[3].powf(2.0)
+    )/(-g(0,0,rs)(p))).sqrt();
 }
 
 pub fn vec4to3(v: &Vector4<f64>) -> Vector3<f64> {
@@ -158,7 +119,7 @@ pub fn cart2sph(v: &Vector3<f64>) -> Vector3<f64> {
     }
     
     if v[2] < 0.0 {
-        v[1] += std::f64::consts::TAU;
+        v[2] += std::f64::consts::TAU;
     }
     
     v
@@ -172,27 +133,22 @@ pub fn sph2cart(v: &Vector3<f64>) -> Vector3<f64> {
     )
 }
 
+// Components of the cartesian `v` in the spherical basis at the spherical `p`
 pub fn cart2sph_at(p: &Vector3<f64>, v: &Vector3<f64>) -> Vector3<f64> {
-    let r_hat = Vector3::new(p[0], p[1], p[2]).normalize();
-    let phi_hat = Vector3::new(-p[2].sin(), p[2].cos(), 0.0);
-    let theta_hat = phi_hat.cross(&r_hat);
-    
+    let (r_hat, theta_hat, phi_hat) = sph_basis(p[1], p[2]);
+
     Vector3::new(
-        v.dot(&r_hat), // r
+        v.dot(&r_hat),
         v.dot(&theta_hat),
-        v.dot(&phi_hat)
-    ) 
+        v.dot(&phi_hat),
+    )
 }
 
-
+// Cartesian vector with components `v` in the spherical basis at the spherical `p`
 pub fn sph2cart_at(p: &Vector3<f64>, v: &Vector3<f64>) -> Vector3<f64> {
-    // p in spherical coords
-    let rot = (
-        Rotation3::from_axis_angle(&Vector3::z_axis(), p[2]) *
-        Rotation3::from_axis_angle(&Vector3::y_axis(), p[1])
-    ).inverse();
+    let (r_hat, theta_hat, phi_hat) = sph_basis(p[1], p[2]);
 
-    rot * v
+    r_hat * v[0] + theta_hat * v[1] + phi_hat * v[2]
 }
 
 pub fn get_pixel_dir(canvas: (f64, f64), fovy: f64, aspect: f64, dir: &Unit<Vector3<f64>>, up: &Unit<Vector3<f64>>) -> Vector3<f64> {
@@ -200,7 +156,7 @@ pub fn get_pixel_dir(canvas: (f64, f64), fovy: f64, aspect: f64, dir: &Unit<Vect
     let canvas_orig = dir.as_ref();
     let dv = dir.cross(up.as_ref()) * (canvas.0 * ys * aspect/2.0) + // x
         up.as_ref() * (canvas.1 * ys/2.0); // y
-    (canvas_orig + &dv).normalize()
+    (canvas_orig + dv).normalize()
 }
 
 #[cfg(test)]
@@ -221,7 +177,7 @@ mod tests {
                     continue;
                 }
 
-                assert_eq!(g(mu, nu)(&pos), 0.0);
+                assert_eq!(g(mu, nu, 1.0)(&pos), 0.0);
             }
         }
     }
@@ -235,8 +191,8 @@ mod tests {
             for mu in 0..4 {
                 for nu in 0..4 {
                     assert_eq!(
-                        gamma(lambda, mu, nu)(&pos),
-                        gamma(lambda, nu, mu)(&pos), 
+                        gamma(lambda, mu, nu, 1.0)(&pos),
+                        gamma(lambda, nu, mu, 1.0)(&pos), 
                         "Failed at {:?}",
                         (lambda, mu, nu)
                     );
@@ -253,12 +209,12 @@ mod tests {
         let v = na::Vector4::new(0.0, random(), random(), random());
         let mut v = 100.0 * v;
 
-        time_norm(&pos, &mut v);
+        time_norm(&pos, &mut v, 1.0);
 
         let mut s = 0.0;
         for mu in 0..4 {
             for nu in 0..4 {
-                s += g(mu, nu)(&pos) * v[mu] * v[nu];
+                s += g(mu, nu, 1.0)(&pos) * v[mu] * v[nu];
             }
         }
         assert!(s < 0.01);
@@ -1,10 +1,12 @@
 use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::thread;
 use std::time::{Instant};
 
 use rayon::prelude::*;
 
-use sdl2::pixels::Color;
+use crate::hdr::Radiance;
+use crate::sampling::{Sampler, Adaptive};
 
 use crate::env::Environment;
 
@@ -12,20 +14,48 @@ pub trait Renderer<T> where
     T: Environment
 {
     fn start_render(&mut self);
-    
-    fn get_pixels(&self) -> Vec<Color>;
+
+    fn get_pixels(&self) -> Vec<Radiance>;
 
     fn is_ready(&self) -> bool;
 }
 
+// Side of the square tiles each rayon task renders
+const TILE_SIZE: u32 = 16;
+
+// Side of the blocks of the first progressive pass, must divide TILE_SIZE
+const COARSEST_BLOCK: u32 = 8;
+
+// Rectangle of pixels, clipped to the screen at the right and bottom edges
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Tile {
+    x: u32,
+    y: u32,
+    w: u32,
+    h: u32,
+}
+
+fn tiles(screen: [u32; 2], size: u32) -> Vec<Tile> {
+    let mut tiles = Vec::new();
+    for y in (0..screen[1]).step_by(size as usize) {
+        for x in (0..screen[0]).step_by(size as usize) {
+            tiles.push(Tile {x, y, w: size.min(screen[0] - x), h: size.min(screen[1] - y)});
+        }
+    }
+    tiles
+}
+
 pub struct RayonRenderer<T> where
     T: Environment
 {
     screen: [u32; 2],
     pub env: T,
-    pixels: Arc<Mutex<Vec<Color>>>,
-    running: Arc<Mutex<bool>>,
+    pixels: Arc<Mutex<Vec<Radiance>>>,
+    running: Arc<AtomicBool>,
     render_thread: Option<thread::JoinHandle<()>>,
+    progressive: bool,
+    sampler: Sampler,
+    adaptive: Option<Adaptive>,
 }
 
 
@@ -33,19 +63,40 @@ impl<T> RayonRenderer<T> where
     T: Environment
 {
     pub fn new(screen: [u32;2], env: T) -> RayonRenderer<T> {
-        let pixels = Arc::new(Mutex::new(vec![Color::RGB(0xff,0xff,0xff);(screen[0]*screen[1]) as usize]));
-        let running = Arc::new(Mutex::new(false));
+        let pixels = Arc::new(Mutex::new(vec![Radiance::new(1.0, 1.0, 1.0);(screen[0]*screen[1]) as usize]));
+        let running = Arc::new(AtomicBool::new(false));
 
         let render_thread = None;
-        RayonRenderer {screen, env, pixels, running, render_thread}
+        RayonRenderer {screen, env, pixels, running, render_thread, progressive: false, sampler: Sampler::default(), adaptive: None}
+    }
+
+    // Renders at 1/8 of the resolution first, then refines in passes of
+    // doubling resolution. Pixels traced in a pass are reused by the next, so
+    // the final image is the same as a single pass.
+    pub fn new_progressive(screen: [u32;2], env: T) -> RayonRenderer<T> {
+        let mut renderer = RayonRenderer::new(screen, env);
+        renderer.progressive = true;
+        renderer
+    }
+
+    // Supersamples every pixel with `sampler`
+    pub fn new_sampled(screen: [u32;2], env: T, sampler: Sampler) -> RayonRenderer<T> {
+        let mut renderer = RayonRenderer::new(screen, env);
+        renderer.sampler = sampler;
+        renderer
+    }
+
+    // Traces every pixel center, then spends the rest of the budget of
+    // `adaptive` where the image has the most contrast
+    pub fn new_adaptive(screen: [u32;2], env: T, adaptive: Adaptive) -> RayonRenderer<T> {
+        let mut renderer = RayonRenderer::new(screen, env);
+        renderer.adaptive = Some(adaptive);
+        renderer
     }
 
     pub fn stop_render(&mut self) {
         if let Some(join_handle) = self.render_thread.take() {
-            {
-                let mut running = self.running.lock().unwrap();
-                *running = false;
-            }
+            self.running.store(false, Ordering::Release);
             join_handle.join().unwrap();
         }
     }
@@ -65,52 +116,154 @@ impl<T> Renderer<T> for RayonRenderer<T> where
     fn start_render(&mut self) {
         self.stop_render();
 
-        {
-            let mut running = self.running.lock().unwrap();
-            *running = true;
-        }
+        self.running.store(true, Ordering::Release);
 
         let a_running = self.running.clone();
         let a_pixels = self.pixels.clone();
-        
-        let screen = self.screen.clone();
+
+        let screen = self.screen;
         let env = self.env.clone();
-        
+        let progressive = self.progressive;
+        let sampler = self.sampler;
+        let adaptive = self.adaptive;
+
         self.render_thread = Some(thread::spawn(move || {
             let t0 = Instant::now();
-            (0..screen[0]*screen[1]).into_par_iter().for_each(|ii| {
-                // Check if quit
-                {
-                    let running = a_running.lock().unwrap();
-                    if !*running {
+            let coarsest = if progressive { COARSEST_BLOCK } else { 1 };
+            let mut block = coarsest;
+            loop {
+                tiles(screen, TILE_SIZE).into_par_iter().for_each(|tile| {
+                    // Check if quit
+                    if !a_running.load(Ordering::Acquire) {
                         return;
                     }
-                }
 
-                let i = ii / screen[0];
-                let j = ii % screen[0];
-                let pixel_color = env.render_pixel(j, i, screen);
-                {
+                    let mut buffer = Vec::new();
+                    for i in (tile.y..tile.y + tile.h).step_by(block as usize) {
+                        for j in (tile.x..tile.x + tile.w).step_by(block as usize) {
+                            // Already traced by the previous pass
+                            if block < coarsest && i % (2*block) == 0 && j % (2*block) == 0 {
+                                continue;
+                            }
+                            buffer.push((j, i, sampler.render_pixel(&env, j, i, screen)));
+                        }
+                    }
+
+                    // Publish the whole tile at once, each sample filling its block
                     let mut pixels = a_pixels.lock().unwrap();
-                    pixels[(i*screen[0] + j) as usize] = pixel_color;
+                    for (j, i, radiance) in buffer {
+                        for y in i..(i + block).min(tile.y + tile.h) {
+                            let start = (y*screen[0] + j) as usize;
+                            let end = (y*screen[0] + (j + block).min(tile.x + tile.w)) as usize;
+                            for pixel in &mut pixels[start..end] {
+                                *pixel = radiance;
+                            }
+                        }
+                    }
+                });
+
+                if block == 1 {
+                    break;
                 }
-            });
-            
-            {
-                let mut running = a_running.lock().unwrap();
-                *running = false;
+                block /= 2;
             }
 
+            if let Some(adaptive) = adaptive.filter(|_| a_running.load(Ordering::Acquire)) {
+                let base = a_pixels.lock().unwrap().clone();
+                let extra = adaptive.allocate(screen, &base);
+
+                tiles(screen, TILE_SIZE).into_par_iter().for_each(|tile| {
+                    // Check if quit
+                    if !a_running.load(Ordering::Acquire) {
+                        return;
+                    }
+
+                    let mut buffer = Vec::new();
+                    for i in tile.y..tile.y + tile.h {
+                        for j in tile.x..tile.x + tile.w {
+                            let ii = (i*screen[0] + j) as usize;
+                            if extra[ii] > 0 {
+                                buffer.push((ii, adaptive.refine(&env, base[ii], extra[ii], j, i, screen)));
+                            }
+                        }
+                    }
 
-            println!("Finished rendering in {}s", t0.elapsed().as_nanos() as f64 / 1e9 );
+                    let mut pixels = a_pixels.lock().unwrap();
+                    for (ii, radiance) in buffer {
+                        pixels[ii] = radiance;
+                    }
+                });
+            }
+
+            // Not cancelled in the meantime
+            if a_running.swap(false, Ordering::AcqRel) {
+                println!("Finished rendering in {}s", t0.elapsed().as_nanos() as f64 / 1e9 );
+            }
         }));
     }
 
-    fn get_pixels(&self) -> Vec<Color> {
+    fn get_pixels(&self) -> Vec<Radiance> {
         self.pixels.lock().unwrap().clone()
     }
 
     fn is_ready(&self) -> bool {
-        !*self.running.lock().unwrap()
+        !self.running.load(Ordering::Acquire)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use rand::prelude::*;
+    use nalgebra::{Unit, Vector3};
+
+    // Different radiance at every pixel center
+    #[derive(Clone)]
+    struct Gradient;
+
+    impl Environment for Gradient {
+        fn raytrace(&self, (x, y): (f64, f64)) -> Radiance {
+            Radiance::new(x as f32, y as f32, (x*y) as f32)
+        }
+
+        fn get_data(&self) -> (Vector3<f64>, Unit<Vector3<f64>>, Unit<Vector3<f64>>) {
+            (Vector3::zeros(), Vector3::x_axis(), Vector3::z_axis())
+        }
+
+        fn set_data(&mut self, _pos: &Vector3<f64>, _dir: &Vector3<f64>, _up: &Vector3<f64>) {}
+    }
+
+    fn render(mut renderer: RayonRenderer<Gradient>) -> Vec<Radiance> {
+        renderer.start_render();
+        while !renderer.is_ready() {
+            thread::sleep(std::time::Duration::from_millis(1));
+        }
+        renderer.get_pixels()
+    }
+
+    #[test]
+    fn progressive_matches_single_pass() {
+        let screen = [1 + random::<u32>() % 100, 1 + random::<u32>() % 100];
+
+        let single = render(RayonRenderer::new(screen, Gradient));
+        let progressive = render(RayonRenderer::new_progressive(screen, Gradient));
+        assert!(single == progressive, "Failed at {:?}", screen);
+    }
+
+    #[test]
+    fn tiles_cover_screen_once() {
+        let screen = [1 + random::<u32>() % 100, 1 + random::<u32>() % 100];
+        let mut covered = vec![0; (screen[0]*screen[1]) as usize];
+
+        for tile in tiles(screen, TILE_SIZE) {
+            for i in tile.y..tile.y + tile.h {
+                for j in tile.x..tile.x + tile.w {
+                    covered[(i*screen[0] + j) as usize] += 1;
+                }
+            }
+        }
+
+        assert!(covered.iter().all(|&c| c == 1), "Failed at {:?}", screen);
     }
 }
@@ -0,0 +1,297 @@
+use crate::env::Environment;
+use crate::hdr::Radiance;
+
+
+// Reconstruction filter, separable, in units of pixels from the pixel center
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Filter {
+    Box,
+    Tent,
+    Mitchell, // B = C = 1/3
+}
+
+impl Filter {
+    // Names as accepted by the CLI
+    pub fn from_name(name: &str) -> Option<Filter> {
+        match name {
+            "box" => Some(Filter::Box),
+            "tent" => Some(Filter::Tent),
+            "mitchell" => Some(Filter::Mitchell),
+            _ => None,
+        }
+    }
+
+    // Half width of the support
+    pub fn radius(&self) -> f64 {
+        match self {
+            Self::Box => 0.5,
+            Self::Tent => 1.0,
+            Self::Mitchell => 2.0,
+        }
+    }
+
+    pub fn weight_1d(&self, x: f64) -> f64 {
+        let x = x.abs();
+        match self {
+            Self::Box => if x <= 0.5 { 1.0 } else { 0.0 },
+            Self::Tent => (1.0 - x).max(0.0),
+            Self::Mitchell => {
+                let (b, c) = (1.0/3.0, 1.0/3.0);
+                if x < 1.0 {
+                    ((12.0 - 9.0*b - 6.0*c)*x.powi(3) + (-18.0 + 12.0*b + 6.0*c)*x.powi(2) + (6.0 - 2.0*b))/6.0
+                } else if x < 2.0 {
+                    ((-b - 6.0*c)*x.powi(3) + (6.0*b + 30.0*c)*x.powi(2) + (-12.0*b - 48.0*c)*x + (8.0*b + 24.0*c))/6.0
+                } else {
+                    0.0
+                }
+            },
+        }
+    }
+
+    pub fn weight(&self, dx: f64, dy: f64) -> f64 {
+        self.weight_1d(dx) * self.weight_1d(dy)
+    }
+}
+
+// Shoots `spp` rays per pixel, jittered inside the strata of a grid over the
+// filter support, and averages them weighted by the filter
+#[derive(Clone, Copy, Debug)]
+pub struct Sampler {
+    pub spp: u32,
+    pub filter: Filter,
+}
+
+impl Sampler {
+    pub fn new(spp: u32, filter: Filter) -> Sampler {
+        Sampler {spp: spp.max(1), filter}
+    }
+
+    // Offsets from the pixel center of the samples of pixel (x, y)
+    pub fn offsets(&self, x: u32, y: u32) -> Vec<(f64, f64)> {
+        // A single sample goes through the center, as without supersampling
+        if self.spp == 1 {
+            return vec![(0.0, 0.0)];
+        }
+
+        self.jittered_offsets(x, y)
+    }
+
+    fn jittered_offsets(&self, x: u32, y: u32) -> Vec<(f64, f64)> {
+        let cols = (self.spp as f64).sqrt().ceil() as u32;
+        let rows = self.spp.div_ceil(cols);
+        let r = self.filter.radius();
+
+        (0..self.spp).map(|i| {
+            let (col, row) = (i % cols, i / cols);
+            // A partial last row spreads over the full width, and is as much
+            // shorter so that every stratum has the same area
+            let row_cols = if row == rows - 1 { self.spp - row * cols } else { cols };
+            let u = (col as f64 + jitter(x, y, 2*i)) / row_cols as f64;
+            let v = ((row * cols) as f64 + jitter(x, y, 2*i + 1) * row_cols as f64) / self.spp as f64;
+            ((2.0*u - 1.0)*r, (2.0*v - 1.0)*r)
+        }).collect()
+    }
+
+    pub fn render_pixel<E: Environment>(&self, env: &E, x: u32, y: u32, screen: [u32; 2]) -> Radiance {
+        let (sum, weights) = self.accumulate(env, &self.offsets(x, y), x, y, screen);
+
+        if weights > 0.0 {
+            normalize(sum, weights)
+        } else {
+            env.render_pixel(x, y, screen)
+        }
+    }
+
+    // Filter weighted sum of the samples at `offsets`, and the sum of weights
+    fn accumulate<E: Environment>(&self, env: &E, offsets: &[(f64, f64)], x: u32, y: u32, screen: [u32; 2]) -> (Radiance, f64) {
+        let (mut sum, mut weights) = (Radiance::zeros(), 0.0);
+        for &(dx, dy) in offsets {
+            let w = self.filter.weight(dx, dy);
+            if w == 0.0 {
+                continue;
+            }
+
+            let radiance = env.render_sample(x as f64 + 0.5 + dx, y as f64 + 0.5 + dy, screen);
+            sum += radiance * w as f32;
+            weights += w;
+        }
+        (sum, weights)
+    }
+}
+
+impl Default for Sampler {
+    fn default() -> Sampler {
+        Sampler::new(1, Filter::Box)
+    }
+}
+
+// Negative lobes can ring below zero around sharp edges
+fn normalize(sum: Radiance, weights: f64) -> Radiance {
+    (sum / weights as f32).map(|c| c.max(0.0))
+}
+
+// Spends an average of `budget` samples per pixel: one through every pixel
+// center first, then the rest where neighbouring pixels differ the most, at
+// most `max_spp` in a single pixel
+#[derive(Clone, Copy, Debug)]
+pub struct Adaptive {
+    pub budget: f64,
+    pub max_spp: u32,
+    pub filter: Filter,
+}
+
+impl Adaptive {
+    pub fn new(budget: f64, max_spp: u32, filter: Filter) -> Adaptive {
+        Adaptive {budget: budget.max(1.0), max_spp: max_spp.max(1), filter}
+    }
+
+    // Largest difference with the 4 neighbours of every pixel, after
+    // compressing each channel to [0, 1) so that bright pixels don't take all
+    // the samples
+    pub fn contrast(screen: [u32; 2], pixels: &[Radiance]) -> Vec<f32> {
+        let compressed: Vec<Radiance> = pixels.iter().map(|p| p.map(|c| c.max(0.0)/(1.0 + c.max(0.0)))).collect();
+        let (w, h) = (screen[0] as i64, screen[1] as i64);
+
+        (0..w*h).map(|ii| {
+            let (i, j) = (ii / w, ii % w);
+            [(-1, 0), (1, 0), (0, -1), (0, 1)].iter()
+                .filter(|(di, dj)| i + di >= 0 && i + di < h && j + dj >= 0 && j + dj < w)
+                .map(|(di, dj)| (compressed[ii as usize] - compressed[((i + di)*w + j + dj) as usize]).abs().max())
+                .fold(0.0, f32::max)
+        }).collect()
+    }
+
+    // Extra samples for every pixel on top of the first one, proportional to
+    // the contrast and within the budget
+    pub fn allocate(&self, screen: [u32; 2], pixels: &[Radiance]) -> Vec<u32> {
+        let contrast = Adaptive::contrast(screen, pixels);
+        let total: f64 = contrast.iter().map(|&c| c as f64).sum();
+        let extra = (self.budget - 1.0) * pixels.len() as f64;
+        if total == 0.0 {
+            return vec![0; pixels.len()];
+        }
+
+        contrast.iter()
+            .map(|&c| ((extra * c as f64 / total).floor() as u32).min(self.max_spp - 1))
+            .collect()
+    }
+
+    // Refines the pixel `base` traced through the center with `extra` jittered samples
+    pub fn refine<E: Environment>(&self, env: &E, base: Radiance, extra: u32, x: u32, y: u32, screen: [u32; 2]) -> Radiance {
+        let sampler = Sampler::new(extra, self.filter);
+        let (sum, weights) = sampler.accumulate(env, &sampler.jittered_offsets(x, y), x, y, screen);
+
+        let w0 = self.filter.weight(0.0, 0.0);
+        normalize(sum + base * w0 as f32, weights + w0)
+    }
+}
+
+// PCG hash, cheap and well mixed random bits from a 32 bit input
+fn pcg_hash(input: u32) -> u32 {
+    let state = input.wrapping_mul(747_796_405).wrapping_add(2_891_336_453);
+    let word = ((state >> ((state >> 28) + 4)) ^ state).wrapping_mul(277_803_737);
+    (word >> 22) ^ word
+}
+
+// Uniform in [0, 1), the same for the same pixel and index so renders are
+// reproducible
+fn jitter(x: u32, y: u32, i: u32) -> f64 {
+    pcg_hash(x ^ pcg_hash(y ^ pcg_hash(i))) as f64 / 4_294_967_296.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use rand::prelude::*;
+    use nalgebra::{Unit, Vector3};
+
+    #[test]
+    fn filters_integrate_to_one() {
+        for &filter in &[Filter::Box, Filter::Tent, Filter::Mitchell] {
+            let steps = 10_000;
+            let h = 2.0*filter.radius()/steps as f64;
+            let total: f64 = (0..steps).map(|i| filter.weight_1d(-filter.radius() + (i as f64 + 0.5)*h) * h).sum();
+            assert!((total - 1.0).abs() < 1e-3, "Failed for {:?}", filter);
+        }
+    }
+
+    #[test]
+    fn samples_are_stratified_in_support() {
+        let spp = 2 + random::<u32>() % 30;
+        for &filter in &[Filter::Box, Filter::Tent, Filter::Mitchell] {
+            let sampler = Sampler::new(spp, filter);
+            let offsets = sampler.offsets(random(), random());
+            assert_eq!(offsets.len(), spp as usize);
+
+            let r = filter.radius();
+            assert!(offsets.iter().all(|(dx, dy)| dx.abs() <= r && dy.abs() <= r));
+
+            // Every column of strata gets a sample
+            let cols = (spp as f64).sqrt().ceil() as u32;
+            for col in 0..cols {
+                let (lo, hi) = (-r + 2.0*r*col as f64/cols as f64, -r + 2.0*r*(col + 1) as f64/cols as f64);
+                assert!(offsets.iter().any(|(dx, _)| *dx >= lo && *dx <= hi), "Failed at {}", spp);
+            }
+        }
+    }
+
+    #[test]
+    fn samples_are_centered() {
+        for &spp in &[2, 3, 5, 7, 11] {
+            let sampler = Sampler::new(spp, Filter::Box);
+            let pixels = 1000;
+            let (mut mean_x, mut mean_y) = (0.0, 0.0);
+            for _ in 0..pixels {
+                for (dx, dy) in sampler.offsets(random(), random()) {
+                    mean_x += dx / (pixels * spp) as f64;
+                    mean_y += dy / (pixels * spp) as f64;
+                }
+            }
+            assert!(mean_x.abs() < 0.01 && mean_y.abs() < 0.01, "Failed at {:?}", (spp, mean_x, mean_y));
+        }
+    }
+
+    // Same radiance everywhere
+    #[derive(Clone)]
+    struct Constant;
+
+    impl Environment for Constant {
+        fn raytrace(&self, _canvas: (f64, f64)) -> Radiance {
+            Radiance::new(0.25, 0.5, 1.0)
+        }
+
+        fn get_data(&self) -> (Vector3<f64>, Unit<Vector3<f64>>, Unit<Vector3<f64>>) {
+            (Vector3::zeros(), Vector3::x_axis(), Vector3::z_axis())
+        }
+
+        fn set_data(&mut self, _pos: &Vector3<f64>, _dir: &Vector3<f64>, _up: &Vector3<f64>) {}
+    }
+
+    #[test]
+    fn adaptive_stays_within_budget_on_edges() {
+        let screen = [20 + random::<u32>() % 50, 20 + random::<u32>() % 50];
+        let edge = screen[0] / 2;
+        let pixels: Vec<Radiance> = (0..screen[0]*screen[1])
+            .map(|ii| if ii % screen[0] < edge { Radiance::zeros() } else { Radiance::new(1.0, 1.0, 1.0) })
+            .collect();
+
+        let adaptive = Adaptive::new(4.0, 1000, Filter::Box);
+        let extra = adaptive.allocate(screen, &pixels);
+
+        let total: u32 = extra.iter().sum::<u32>() + screen[0]*screen[1];
+        assert!(total as f64 <= 4.0 * (screen[0]*screen[1]) as f64);
+        for (ii, &n) in extra.iter().enumerate() {
+            let j = ii as u32 % screen[0];
+            let on_edge = j == edge || j + 1 == edge;
+            assert_eq!(n > 0, on_edge, "Failed at {:?}", (ii, n));
+        }
+    }
+
+    #[test]
+    fn weighted_average_keeps_constants() {
+        let sampler = Sampler::new(16, Filter::Mitchell);
+        let radiance = sampler.render_pixel(&Constant, 3, 4, [10, 10]);
+        assert!((radiance - Constant.raytrace((0.0, 0.0))).norm() < 1e-6);
+    }
+}
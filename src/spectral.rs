@@ -0,0 +1,108 @@
+use nalgebra::{Matrix3, Vector3};
+
+use crate::units::{BOLTZMANN, C, PLANCK};
+
+
+// Visible range and step, in nm, used to integrate spectra
+const LAMBDA_MIN: f64 = 360.0;
+const LAMBDA_MAX: f64 = 830.0;
+const LAMBDA_STEP: f64 = 5.0;
+
+// Maximum luminous efficacy, in lm/W, so that Y is a luminance in cd/m^2
+const LUMINOUS_EFFICACY: f64 = 683.0;
+
+// Spectral radiance of a blackbody at `temperature`, in W sr^-1 m^-2 per m of
+// wavelength, at `wavelength` in m
+pub fn planck(wavelength: f64, temperature: f64) -> f64 {
+    2.0*PLANCK*C.powi(2)/wavelength.powi(5)
+        / ((PLANCK*C/(wavelength*BOLTZMANN*temperature)).exp_m1())
+}
+
+// CIE 1931 2° color matching functions at `wavelength` in nm, from the
+// multi-lobe fit of Wyman, Sloan & Shirley (2013)
+pub fn cie_xyz(wavelength: f64) -> Vector3<f64> {
+    // Gaussian with a different width on each side of the peak
+    let lobe = |mu: f64, sigma_below: f64, sigma_above: f64| {
+        let sigma = if wavelength < mu { sigma_below } else { sigma_above };
+        (-0.5*((wavelength - mu)/sigma).powi(2)).exp()
+    };
+
+    Vector3::new(
+        1.056*lobe(599.8, 37.9, 31.0) + 0.362*lobe(442.0, 16.0, 26.7) - 0.065*lobe(501.1, 20.4, 26.2),
+        0.821*lobe(568.8, 46.9, 40.5) + 0.286*lobe(530.9, 16.3, 31.1),
+        1.217*lobe(437.0, 11.8, 36.0) + 0.681*lobe(459.0, 26.0, 13.8),
+    )
+}
+
+// Tristimulus values of a blackbody at `temperature`, Y in cd/m^2
+pub fn blackbody_xyz(temperature: f64) -> Vector3<f64> {
+    let steps = ((LAMBDA_MAX - LAMBDA_MIN)/LAMBDA_STEP) as usize;
+
+    let xyz = (0..=steps).fold(Vector3::zeros(), |acc, i| {
+        let lambda = LAMBDA_MIN + i as f64 * LAMBDA_STEP;
+        acc + cie_xyz(lambda) * planck(lambda * 1e-9, temperature)
+    });
+
+    xyz * LAMBDA_STEP * 1e-9 * LUMINOUS_EFFICACY
+}
+
+// XYZ to linear sRGB, D65 white point
+pub fn xyz_to_linear_srgb(xyz: &Vector3<f64>) -> Vector3<f64> {
+    let m = Matrix3::new(
+         3.240_454_2, -1.537_138_5, -0.498_531_4,
+        -0.969_266_0,  1.876_010_8,  0.041_556_0,
+         0.055_643_4, -0.204_025_9,  1.057_225_2,
+    );
+
+    m * xyz
+}
+
+// Linear sRGB radiance of a blackbody at `temperature`, in cd/m^2
+pub fn blackbody_radiance(temperature: f64) -> Vector3<f64> {
+    xyz_to_linear_srgb(&blackbody_xyz(temperature))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use rand::prelude::*;
+
+    use crate::units::STEFAN_BOLTZMANN;
+
+    #[test]
+    fn planck_integrates_to_stefan_boltzmann() {
+        let temperature = 2e3 + 1e4 * random::<f64>();
+
+        // Trapezoids in log wavelength, from 10 nm to 1 mm
+        let (min, max, steps) = (1e-8f64.ln(), 1e-3f64.ln(), 100_000);
+        let h = (max - min)/steps as f64;
+        let total: f64 = (0..=steps).map(|i| {
+            let lambda = (min + i as f64 * h).exp();
+            let weight = if i == 0 || i == steps { 0.5 } else { 1.0 };
+            weight * planck(lambda, temperature) * lambda * h
+        }).sum();
+
+        let expected = STEFAN_BOLTZMANN * temperature.powi(4) / std::f64::consts::PI;
+        assert!((total/expected - 1.0).abs() < 1e-4, "Failed at {} K", temperature);
+    }
+
+    #[test]
+    fn d65_is_white() {
+        let rgb = xyz_to_linear_srgb(&Vector3::new(0.950_47, 1.0, 1.088_83));
+        for i in 0..3 {
+            assert!((rgb[i] - 1.0).abs() < 1e-3);
+        }
+    }
+
+    #[test]
+    fn blackbody_chromaticity() {
+        // Planckian locus, CIE 1931
+        for &(temperature, x, y) in &[(2856.0, 0.4476, 0.4074), (6504.0, 0.3135, 0.3236)] {
+            let xyz = blackbody_xyz(temperature);
+            let sum = xyz.sum();
+            assert!((xyz[0]/sum - x).abs() < 3e-3, "Failed at {} K", temperature);
+            assert!((xyz[1]/sum - y).abs() < 3e-3, "Failed at {} K", temperature);
+        }
+    }
+}
@@ -0,0 +1,98 @@
+use crate::hdr::Radiance;
+use crate::color::Color;
+
+
+// Curve compressing linear radiance into [0, 1]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Operator {
+    Clamp,
+    Reinhard,
+    Aces,
+}
+
+impl Operator {
+    // Names as accepted by the CLI
+    pub fn from_name(name: &str) -> Option<Operator> {
+        match name {
+            "clamp" => Some(Operator::Clamp),
+            "reinhard" => Some(Operator::Reinhard),
+            "aces" => Some(Operator::Aces),
+            _ => None,
+        }
+    }
+
+    pub fn apply(&self, c: f32) -> f32 {
+        match self {
+            Self::Clamp => c,
+            Self::Reinhard => c / (1.0 + c),
+            // Narkowicz's fit of the ACES filmic curve
+            Self::Aces => (c*(2.51*c + 0.03)) / (c*(2.43*c + 0.59) + 0.14),
+        }.clamp(0.0, 1.0)
+    }
+}
+
+// Turns linear radiance into displayable 8 bit color
+#[derive(Clone, Copy, Debug)]
+pub struct ToneMapping {
+    pub exposure: f32,
+    pub operator: Operator,
+    pub gamma: Option<f32>, // the sRGB curve if none
+}
+
+impl ToneMapping {
+    pub fn new(exposure: f32, operator: Operator, gamma: Option<f32>) -> ToneMapping {
+        ToneMapping {exposure, operator, gamma}
+    }
+
+    pub fn apply(&self, radiance: &Radiance) -> Color {
+        let encode = |c: f32| {
+            let c = self.operator.apply(c * self.exposure);
+            let c = match self.gamma {
+                Some(gamma) => c.powf(1.0/gamma),
+                None if c <= 0.003_130_8 => 12.92 * c,
+                None => 1.055 * c.powf(1.0/2.4) - 0.055,
+            };
+            (255.0 * c).round() as u8
+        };
+
+        Color::rgb(encode(radiance[0]), encode(radiance[1]), encode(radiance[2]))
+    }
+}
+
+impl Default for ToneMapping {
+    fn default() -> ToneMapping {
+        ToneMapping::new(1.0, Operator::Clamp, None)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use rand::prelude::*;
+
+    #[test]
+    fn operators_are_monotonic_and_bounded() {
+        for &operator in &[Operator::Clamp, Operator::Reinhard, Operator::Aces] {
+            assert_eq!(operator.apply(0.0), 0.0);
+
+            let mut last = 0.0;
+            for i in 1..1000 {
+                let c = operator.apply(i as f32 * 0.01);
+                assert!(c >= last && c <= 1.0, "Failed for {:?}", operator);
+                last = c;
+            }
+        }
+    }
+
+    #[test]
+    fn default_maps_white_to_white() {
+        let tone_mapping = ToneMapping::default();
+        assert_eq!(tone_mapping.apply(&Radiance::new(1.0, 0.0, 2.0)), Color::rgb(0xff, 0x00, 0xff));
+
+        let c = random::<f32>();
+        let tone_mapping = ToneMapping::new(1.0, Operator::Clamp, Some(2.2));
+        let encoded = tone_mapping.apply(&Radiance::new(c, c, c)).r as f32 / 255.0;
+        assert!((encoded.powf(2.2) - c).abs() < 0.02);
+    }
+}
@@ -0,0 +1,83 @@
+// Conversions between physical units and geometric units (G = c = 1), where
+// masses and times are measured as lengths.
+
+// Newton's constant, in m^3 kg^-1 s^-2
+pub const G: f64 = 6.674_30e-11;
+
+// Speed of light, in m/s
+pub const C: f64 = 299_792_458.0;
+
+// Mass of the Sun, in kg
+pub const SOLAR_MASS: f64 = 1.988_47e30;
+
+// Stefan-Boltzmann constant, in W m^-2 K^-4
+pub const STEFAN_BOLTZMANN: f64 = 5.670_374e-8;
+
+// Planck constant, in J s
+pub const PLANCK: f64 = 6.626_070_15e-34;
+
+// Boltzmann constant, in J/K
+pub const BOLTZMANN: f64 = 1.380_649e-23;
+
+// Julian year, in s
+pub const YEAR: f64 = 3.155_76e7;
+
+// Mass in kg to its geometric length G M / c^2, in m
+pub fn kg_to_m(kg: f64) -> f64 {
+    G * kg / C.powi(2)
+}
+
+pub fn m_to_kg(m: f64) -> f64 {
+    m * C.powi(2) / G
+}
+
+// Mass in solar masses to its geometric length, in km
+pub fn solar_masses_to_km(solar_masses: f64) -> f64 {
+    kg_to_m(solar_masses * SOLAR_MASS) / 1e3
+}
+
+pub fn km_to_solar_masses(km: f64) -> f64 {
+    m_to_kg(km * 1e3) / SOLAR_MASS
+}
+
+// Time in s to its geometric length c t, in m
+pub fn s_to_m(s: f64) -> f64 {
+    C * s
+}
+
+pub fn m_to_s(m: f64) -> f64 {
+    m / C
+}
+
+// Length in km to geometric units where the hole has mass `mass`, i.e. to
+// the scene units of an environment whose black hole weighs `solar_masses`
+pub fn km_to_geometric(km: f64, solar_masses: f64, mass: f64) -> f64 {
+    km / solar_masses_to_km(solar_masses) * mass
+}
+
+pub fn geometric_to_km(length: f64, solar_masses: f64, mass: f64) -> f64 {
+    length / mass * solar_masses_to_km(solar_masses)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use rand::prelude::*;
+
+    #[test]
+    fn sun_schwarzschild_radius() {
+        let rs = 2.0 * solar_masses_to_km(1.0);
+        assert!((rs - 2.953).abs() < 1e-3);
+    }
+
+    #[test]
+    fn conversions_are_inverses() {
+        let x: f64 = 100.0 * random::<f64>();
+
+        assert!((km_to_solar_masses(solar_masses_to_km(x)) - x).abs() < 1e-9 * x);
+        assert!((m_to_kg(kg_to_m(x)) - x).abs() < 1e-9 * x);
+        assert!((m_to_s(s_to_m(x)) - x).abs() < 1e-9 * x);
+        assert!((geometric_to_km(km_to_geometric(x, 10.0, 0.5), 10.0, 0.5) - x).abs() < 1e-9 * x);
+    }
+}